@@ -0,0 +1,11 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the bundled protoc unless the environment provides one
+    if std::env::var_os("PROTOC").is_none() {
+        // SAFETY: build scripts are single-threaded
+        unsafe { std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?) };
+    }
+    tonic_prost_build::compile_protos("proto/allowlist.proto")?;
+    // `embed_migrations!` reads this directory at compile time
+    println!("cargo:rerun-if-changed=migrations");
+    Ok(())
+}
@@ -0,0 +1,2 @@
+-- Drop the bloom_allowlist table; its index goes with it
+DROP TABLE bloom_allowlist;
@@ -0,0 +1,10 @@
+-- Collapse back to a single global list, keeping the first row per wallet
+DELETE FROM bloom_allowlist a
+    USING bloom_allowlist b
+WHERE a.wallet_address = b.wallet_address AND a.id > b.id;
+
+ALTER TABLE bloom_allowlist DROP CONSTRAINT bloom_allowlist_list_wallet_key;
+ALTER TABLE bloom_allowlist ADD CONSTRAINT bloom_allowlist_wallet_address_key UNIQUE (wallet_address);
+ALTER TABLE bloom_allowlist DROP COLUMN list_id;
+
+DROP TABLE lists;
@@ -0,0 +1,17 @@
+-- Named allowlists (one per campaign / collection)
+CREATE TABLE lists (
+                       id SERIAL PRIMARY KEY,
+                       name TEXT NOT NULL UNIQUE,
+                       created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Existing wallets move into a list called 'default'
+INSERT INTO lists (name) VALUES ('default');
+
+ALTER TABLE bloom_allowlist ADD COLUMN list_id INTEGER REFERENCES lists(id) ON DELETE CASCADE;
+UPDATE bloom_allowlist SET list_id = (SELECT id FROM lists WHERE name = 'default');
+ALTER TABLE bloom_allowlist ALTER COLUMN list_id SET NOT NULL;
+
+-- A wallet is unique per list, not globally
+ALTER TABLE bloom_allowlist DROP CONSTRAINT bloom_allowlist_wallet_address_key;
+ALTER TABLE bloom_allowlist ADD CONSTRAINT bloom_allowlist_list_wallet_key UNIQUE (list_id, wallet_address);
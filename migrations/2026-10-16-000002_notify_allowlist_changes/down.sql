@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS lists_notify ON lists;
+DROP FUNCTION IF EXISTS notify_list_change();
+DROP TRIGGER IF EXISTS bloom_allowlist_notify ON bloom_allowlist;
+DROP FUNCTION IF EXISTS notify_bloom_allowlist_change();
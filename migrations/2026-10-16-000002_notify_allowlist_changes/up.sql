@@ -0,0 +1,51 @@
+-- Broadcast allowlist changes so every guard replica can update its in-memory filters.
+-- Payloads are JSON, e.g. {"op":"insert","id":42,"list_id":1,"wallet":"0xabc..."}
+CREATE OR REPLACE FUNCTION notify_bloom_allowlist_change() RETURNS trigger AS $$
+DECLARE
+    row RECORD;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        row := OLD;
+    ELSE
+        row := NEW;
+    END IF;
+
+    PERFORM pg_notify('bloom_allowlist_changes', json_build_object(
+        'op', lower(TG_OP),
+        'id', row.id,
+        'list_id', row.list_id,
+        'wallet', row.wallet_address
+    )::text);
+
+    RETURN row;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER bloom_allowlist_notify
+    AFTER INSERT OR DELETE ON bloom_allowlist
+    FOR EACH ROW EXECUTE FUNCTION notify_bloom_allowlist_change();
+
+-- Lists created or deleted on one replica must appear / disappear on the others
+CREATE OR REPLACE FUNCTION notify_list_change() RETURNS trigger AS $$
+DECLARE
+    row RECORD;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        row := OLD;
+    ELSE
+        row := NEW;
+    END IF;
+
+    PERFORM pg_notify('bloom_allowlist_changes', json_build_object(
+        'op', 'list_' || lower(TG_OP),
+        'id', row.id,
+        'name', row.name
+    )::text);
+
+    RETURN row;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER lists_notify
+    AFTER INSERT OR DELETE ON lists
+    FOR EACH ROW EXECUTE FUNCTION notify_list_change();
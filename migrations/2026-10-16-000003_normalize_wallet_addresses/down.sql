@@ -0,0 +1,2 @@
+-- The original casing is gone; lowercase addresses are still valid, so there is nothing to undo.
+SELECT 1;
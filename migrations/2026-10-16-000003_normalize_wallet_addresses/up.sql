@@ -0,0 +1,11 @@
+-- Wallets are now stored in canonical form: 0x + 40 lowercase hex digits.
+-- Drop rows that only differ by case within a list (keeping the oldest), then lowercase the rest.
+DELETE FROM bloom_allowlist a
+    USING bloom_allowlist b
+WHERE a.list_id = b.list_id
+  AND lower(a.wallet_address) = lower(b.wallet_address)
+  AND a.id > b.id;
+
+UPDATE bloom_allowlist
+SET wallet_address = lower(wallet_address)
+WHERE wallet_address <> lower(wallet_address);
@@ -0,0 +1,27 @@
+CREATE OR REPLACE FUNCTION notify_bloom_allowlist_change() RETURNS trigger AS $$
+DECLARE
+    row RECORD;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        row := OLD;
+    ELSE
+        row := NEW;
+    END IF;
+
+    PERFORM pg_notify('bloom_allowlist_changes', json_build_object(
+        'op', lower(TG_OP),
+        'id', row.id,
+        'list_id', row.list_id,
+        'wallet', row.wallet_address
+    )::text);
+
+    RETURN row;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Only EVM wallets survive going back to a single address format
+DELETE FROM bloom_allowlist WHERE chain <> 'evm';
+
+ALTER TABLE bloom_allowlist DROP CONSTRAINT bloom_allowlist_list_chain_wallet_key;
+ALTER TABLE bloom_allowlist ADD CONSTRAINT bloom_allowlist_list_wallet_key UNIQUE (list_id, wallet_address);
+ALTER TABLE bloom_allowlist DROP COLUMN chain;
@@ -0,0 +1,31 @@
+-- Wallets now carry the chain their address belongs to; everything stored so far is EVM
+ALTER TABLE bloom_allowlist
+    ADD COLUMN chain TEXT NOT NULL DEFAULT 'evm'
+        CHECK (chain IN ('evm', 'solana', 'bitcoin', 'cosmos'));
+
+-- The same string may be a valid address on two chains
+ALTER TABLE bloom_allowlist DROP CONSTRAINT bloom_allowlist_list_wallet_key;
+ALTER TABLE bloom_allowlist ADD CONSTRAINT bloom_allowlist_list_chain_wallet_key UNIQUE (list_id, chain, wallet_address);
+
+-- Replicas need the chain to rebuild the filter key
+CREATE OR REPLACE FUNCTION notify_bloom_allowlist_change() RETURNS trigger AS $$
+DECLARE
+    row RECORD;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        row := OLD;
+    ELSE
+        row := NEW;
+    END IF;
+
+    PERFORM pg_notify('bloom_allowlist_changes', json_build_object(
+        'op', lower(TG_OP),
+        'id', row.id,
+        'list_id', row.list_id,
+        'chain', row.chain,
+        'wallet', row.wallet_address
+    )::text);
+
+    RETURN row;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,4 @@
+ALTER TABLE bloom_allowlist
+    DROP CONSTRAINT bloom_allowlist_claims_check,
+    DROP COLUMN claimed,
+    DROP COLUMN max_claims;
@@ -0,0 +1,5 @@
+-- How many times each wallet may claim, and how many times it already has
+ALTER TABLE bloom_allowlist
+    ADD COLUMN max_claims INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN claimed INTEGER NOT NULL DEFAULT 0,
+    ADD CONSTRAINT bloom_allowlist_claims_check CHECK (claimed >= 0 AND claimed <= max_claims);
@@ -0,0 +1,9 @@
+DROP TRIGGER lists_window_notify ON lists;
+DROP TRIGGER phases_notify ON phases;
+DROP FUNCTION notify_schedule_change();
+DROP TABLE phases;
+ALTER TABLE bloom_allowlist DROP COLUMN tier;
+ALTER TABLE lists
+    DROP CONSTRAINT lists_window_check,
+    DROP COLUMN ends_at,
+    DROP COLUMN starts_at;
@@ -0,0 +1,50 @@
+-- A list can be limited to a time window; NULL means unbounded
+ALTER TABLE lists
+    ADD COLUMN starts_at TIMESTAMPTZ,
+    ADD COLUMN ends_at TIMESTAMPTZ,
+    ADD CONSTRAINT lists_window_check CHECK (ends_at IS NULL OR starts_at IS NULL OR ends_at > starts_at);
+
+-- Wallets carry a tier that phases admit, e.g. 'og' or 'whitelist'
+ALTER TABLE bloom_allowlist ADD COLUMN tier TEXT NOT NULL DEFAULT 'default';
+
+-- Phases of a drop (OG, whitelist, public, ...) inside the list's window
+CREATE TABLE phases (
+    id SERIAL PRIMARY KEY,
+    list_id INTEGER NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ,
+    -- Tiers admitted during the phase; empty admits every tier
+    tiers TEXT[] NOT NULL DEFAULT '{}',
+    UNIQUE (list_id, name),
+    CHECK (ends_at IS NULL OR ends_at > starts_at)
+);
+
+-- Replicas reload a list's schedule when its window or phases change.
+-- The trigger argument names the column holding the list id.
+CREATE OR REPLACE FUNCTION notify_schedule_change() RETURNS trigger AS $$
+DECLARE
+    row RECORD;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        row := OLD;
+    ELSE
+        row := NEW;
+    END IF;
+
+    PERFORM pg_notify('bloom_allowlist_changes', json_build_object(
+        'op', 'schedule',
+        'list_id', (to_jsonb(row) ->> TG_ARGV[0])::integer
+    )::text);
+
+    RETURN row;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER phases_notify
+    AFTER INSERT OR UPDATE OR DELETE ON phases
+    FOR EACH ROW EXECUTE FUNCTION notify_schedule_change('list_id');
+
+CREATE TRIGGER lists_window_notify
+    AFTER UPDATE OF starts_at, ends_at ON lists
+    FOR EACH ROW EXECUTE FUNCTION notify_schedule_change('id');
@@ -0,0 +1,61 @@
+-- Notifications without the origin tag, as before
+CREATE OR REPLACE FUNCTION notify_bloom_allowlist_change() RETURNS trigger AS $$
+DECLARE
+    row RECORD;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        row := OLD;
+    ELSE
+        row := NEW;
+    END IF;
+
+    PERFORM pg_notify('bloom_allowlist_changes', json_build_object(
+        'op', lower(TG_OP),
+        'id', row.id,
+        'list_id', row.list_id,
+        'chain', row.chain,
+        'wallet', row.wallet_address
+    )::text);
+
+    RETURN row;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION notify_list_change() RETURNS trigger AS $$
+DECLARE
+    row RECORD;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        row := OLD;
+    ELSE
+        row := NEW;
+    END IF;
+
+    PERFORM pg_notify('bloom_allowlist_changes', json_build_object(
+        'op', 'list_' || lower(TG_OP),
+        'id', row.id,
+        'name', row.name
+    )::text);
+
+    RETURN row;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION notify_schedule_change() RETURNS trigger AS $$
+DECLARE
+    row RECORD;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        row := OLD;
+    ELSE
+        row := NEW;
+    END IF;
+
+    PERFORM pg_notify('bloom_allowlist_changes', json_build_object(
+        'op', 'schedule',
+        'list_id', (to_jsonb(row) ->> TG_ARGV[0])::integer
+    )::text);
+
+    RETURN row;
+END;
+$$ LANGUAGE plpgsql;
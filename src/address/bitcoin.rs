@@ -0,0 +1,92 @@
+use bech32::{hrp, segwit};
+
+use super::{AddressError, AddressFormat, Chain};
+
+// Base58Check version bytes on mainnet
+const P2PKH_VERSION: u8 = 0x00;
+const P2SH_VERSION: u8 = 0x05;
+
+/// Bitcoin mainnet address: legacy Base58Check P2PKH (`1...`) / P2SH (`3...`),
+/// or segwit bech32 / bech32m (`bc1...`). Segwit addresses are stored in lowercase.
+pub struct Bitcoin;
+
+impl AddressFormat for Bitcoin {
+    fn canonicalize(&self, raw: &str) -> Result<String, AddressError> {
+        let malformed = |reason: String| AddressError::Malformed(Chain::Bitcoin, reason);
+
+        if raw.get(..3).is_some_and(|prefix| prefix.eq_ignore_ascii_case("bc1")) {
+            let (prefix, version, program) = segwit::decode(raw).map_err(|e| malformed(e.to_string()))?;
+            if prefix != hrp::BC {
+                return Err(malformed(format!("unexpected prefix '{}'", prefix)));
+            }
+            return segwit::encode(prefix, version, &program).map_err(|e| malformed(e.to_string()));
+        }
+
+        // Payload is the version byte followed by a 20-byte hash
+        let payload = bs58::decode(raw)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| malformed(e.to_string()))?;
+        match payload.as_slice() {
+            [P2PKH_VERSION | P2SH_VERSION, hash @ ..] if hash.len() == 20 => Ok(raw.to_string()),
+            [version, ..] if payload.len() == 21 => {
+                Err(malformed(format!("unsupported version byte 0x{:02x}", version)))
+            }
+            _ => Err(malformed(format!("payload must be 21 bytes, got {}", payload.len()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The genesis block's coinbase address, and the P2SH example from the Bitcoin wiki
+    const P2PKH: &str = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa";
+    const P2SH: &str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+
+    fn malformed(result: Result<String, AddressError>) -> bool {
+        matches!(result, Err(AddressError::Malformed(Chain::Bitcoin, _)))
+    }
+
+    #[test]
+    fn accepts_legacy_addresses_as_is() {
+        assert_eq!(Bitcoin.canonicalize(P2PKH), Ok(P2PKH.to_string()));
+        assert_eq!(Bitcoin.canonicalize(P2SH), Ok(P2SH.to_string()));
+    }
+
+    #[test]
+    fn rejects_legacy_addresses_with_a_bad_checksum() {
+        // Last character changed
+        assert!(malformed(Bitcoin.canonicalize("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb")));
+        assert!(malformed(Bitcoin.canonicalize("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLz")));
+    }
+
+    #[test]
+    fn rejects_other_version_bytes_and_lengths() {
+        // Testnet P2PKH version byte
+        let testnet = bs58::encode([&[0x6f][..], &[7; 20]].concat()).with_check().into_string();
+        assert!(malformed(Bitcoin.canonicalize(&testnet)));
+        let short = bs58::encode([&[P2PKH_VERSION][..], &[7; 19]].concat()).with_check().into_string();
+        assert!(malformed(Bitcoin.canonicalize(&short)));
+    }
+
+    #[test]
+    fn segwit_addresses_are_stored_in_lowercase() {
+        for (version, program) in [(segwit::VERSION_0, vec![7; 20]), (segwit::VERSION_0, vec![7; 32]), (segwit::VERSION_1, vec![7; 32])] {
+            let address = segwit::encode(hrp::BC, version, &program).unwrap();
+            assert_eq!(Bitcoin.canonicalize(&address), Ok(address.clone()));
+            assert_eq!(Bitcoin.canonicalize(&address.to_ascii_uppercase()), Ok(address));
+        }
+    }
+
+    #[test]
+    fn rejects_testnet_and_mixed_case_segwit_addresses() {
+        let testnet = segwit::encode(hrp::TB, segwit::VERSION_0, &[7; 20]).unwrap();
+        assert!(malformed(Bitcoin.canonicalize(&testnet)));
+
+        let address = segwit::encode(hrp::BC, segwit::VERSION_0, &[7; 20]).unwrap();
+        let mixed = format!("BC1{}", &address[3..]);
+        assert!(malformed(Bitcoin.canonicalize(&mixed)));
+    }
+}
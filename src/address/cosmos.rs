@@ -0,0 +1,68 @@
+use bech32::primitives::decode::CheckedHrpstring;
+use bech32::Bech32;
+
+use super::{AddressError, AddressFormat, Chain};
+
+/// Cosmos SDK account address: bech32 (not bech32m) with the chain's prefix
+/// (`cosmos`, `osmo`, ...) over a 20-byte account or 32-byte module / ICA address.
+/// Bech32 is case insensitive, so the canonical form is lowercase.
+pub struct Cosmos;
+
+impl AddressFormat for Cosmos {
+    fn canonicalize(&self, raw: &str) -> Result<String, AddressError> {
+        let malformed = |reason: String| AddressError::Malformed(Chain::Cosmos, reason);
+
+        let checked = CheckedHrpstring::new::<Bech32>(raw).map_err(|e| malformed(e.to_string()))?;
+        let data: Vec<u8> = checked.byte_iter().collect();
+        if data.len() != 20 && data.len() != 32 {
+            return Err(malformed(format!("address must be 20 or 32 bytes, got {}", data.len())));
+        }
+
+        // Re-encoding only differs from the input if it had non-zero padding bits
+        let canonical = bech32::encode::<Bech32>(checked.hrp(), &data).map_err(|e| malformed(e.to_string()))?;
+        if !canonical.eq_ignore_ascii_case(raw) {
+            return Err(malformed("invalid padding".to_string()));
+        }
+
+        Ok(canonical)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bech32::{Bech32m, Hrp};
+
+    fn encode(prefix: &str, data: &[u8]) -> String {
+        bech32::encode::<Bech32>(Hrp::parse(prefix).unwrap(), data).unwrap()
+    }
+
+    fn malformed(result: Result<String, AddressError>) -> bool {
+        matches!(result, Err(AddressError::Malformed(Chain::Cosmos, _)))
+    }
+
+    #[test]
+    fn accepts_account_and_module_addresses_with_any_prefix() {
+        for (prefix, data) in [("cosmos", &[7u8; 20][..]), ("osmo", &[7; 20]), ("cosmos", &[7; 32])] {
+            let address = encode(prefix, data);
+            assert_eq!(Cosmos.canonicalize(&address), Ok(address.clone()));
+            assert_eq!(Cosmos.canonicalize(&address.to_ascii_uppercase()), Ok(address));
+        }
+    }
+
+    #[test]
+    fn rejects_bech32m() {
+        let address = bech32::encode::<Bech32m>(Hrp::parse("cosmos").unwrap(), &[7; 20]).unwrap();
+        assert!(malformed(Cosmos.canonicalize(&address)));
+    }
+
+    #[test]
+    fn rejects_other_lengths_and_bad_checksums() {
+        assert!(malformed(Cosmos.canonicalize(&encode("cosmos", &[7; 21]))));
+
+        let mut address = encode("cosmos", &[7; 20]);
+        let last = if address.ends_with('q') { "p" } else { "q" };
+        address.replace_range(address.len() - 1.., last);
+        assert!(malformed(Cosmos.canonicalize(&address)));
+    }
+}
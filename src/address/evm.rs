@@ -0,0 +1,114 @@
+use tiny_keccak::{Hasher, Keccak};
+
+use super::{AddressError, AddressFormat};
+
+/// 20-byte EVM address, canonical form `0x` + 40 lowercase hex digits.
+/// All-lowercase and all-uppercase input is accepted as is; mixed case must be
+/// a valid EIP-55 checksum, which catches most typos.
+pub struct Evm;
+
+impl AddressFormat for Evm {
+    fn canonicalize(&self, raw: &str) -> Result<String, AddressError> {
+        let digits = raw
+            .strip_prefix("0x")
+            .or_else(|| raw.strip_prefix("0X"))
+            .ok_or(AddressError::MissingPrefix)?;
+
+        if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(AddressError::InvalidCharacter(c));
+        }
+        if digits.len() != 40 {
+            return Err(AddressError::InvalidLength(digits.len()));
+        }
+
+        let lower = digits.to_ascii_lowercase();
+        let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+        let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+        if has_upper && has_lower && checksum_digits(&lower) != digits {
+            return Err(AddressError::BadChecksum);
+        }
+
+        Ok(format!("0x{}", lower))
+    }
+}
+
+/// Canonical form of raw address bytes
+pub fn encode(bytes: &[u8; 20]) -> String {
+    let digits: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", digits)
+}
+
+/// EIP-55: uppercase every hex letter whose nibble in keccak256(lowercase hex) is >= 8
+fn checksum_digits(lower: &str) -> String {
+    let mut hash = [0u8; 32];
+    let mut keccak = Keccak::v256();
+    keccak.update(lower.as_bytes());
+    keccak.finalize(&mut hash);
+
+    lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Examples from the EIP-55 specification
+    const CHECKSUMMED: &[&str] = &[
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+    const ALL_CAPS: &[&str] = &[
+        "0x52908400098527886E0F7030069857D2E4169EE7",
+        "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+    ];
+    const ALL_LOWER: &[&str] = &[
+        "0xde709f2102306220921060314715629080e2fb77",
+        "0x27b1fdb04752bbc536007a920d24acb045561c26",
+    ];
+
+    #[test]
+    fn checksum_matches_eip55_examples() {
+        for address in CHECKSUMMED.iter().chain(ALL_CAPS).chain(ALL_LOWER) {
+            let digits = &address[2..];
+            assert_eq!(checksum_digits(&digits.to_ascii_lowercase()), *digits, "{}", address);
+        }
+    }
+
+    #[test]
+    fn accepts_checksummed_and_single_case_addresses() {
+        for address in CHECKSUMMED.iter().chain(ALL_CAPS).chain(ALL_LOWER) {
+            assert_eq!(Evm.canonicalize(address), Ok(format!("0x{}", address[2..].to_ascii_lowercase())));
+        }
+    }
+
+    #[test]
+    fn rejects_a_broken_checksum() {
+        // Same address as the first example with one letter's case flipped
+        assert_eq!(
+            Evm.canonicalize("0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+            Err(AddressError::BadChecksum)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert_eq!(
+            Evm.canonicalize("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"),
+            Err(AddressError::MissingPrefix)
+        );
+        assert_eq!(Evm.canonicalize("0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea"), Err(AddressError::InvalidLength(38)));
+        assert_eq!(
+            Evm.canonicalize("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaeg"),
+            Err(AddressError::InvalidCharacter('g'))
+        );
+    }
+}
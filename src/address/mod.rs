@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+mod bitcoin;
+mod cosmos;
+mod evm;
+mod solana;
+
+/// Address family a wallet belongs to. Each one has its own encoding and canonical form,
+/// and is stored in `bloom_allowlist.chain`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Chain {
+    /// Ethereum and other EVM chains: `0x` + 40 hex digits
+    #[default]
+    Evm,
+    /// Base58 ed25519 public key
+    Solana,
+    /// Base58Check P2PKH / P2SH, or segwit bech32 / bech32m on mainnet
+    Bitcoin,
+    /// Bech32 account address with any prefix (`cosmos1...`, `osmo1...`)
+    Cosmos,
+}
+
+impl Chain {
+    /// Name used in the `chain` column, in the API and in filter keys
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Chain::Evm => "evm",
+            Chain::Solana => "solana",
+            Chain::Bitcoin => "bitcoin",
+            Chain::Cosmos => "cosmos",
+        }
+    }
+
+    /// Parser for this chain's addresses
+    fn format(&self) -> &'static dyn AddressFormat {
+        match self {
+            Chain::Evm => &evm::Evm,
+            Chain::Solana => &solana::Solana,
+            Chain::Bitcoin => &bitcoin::Bitcoin,
+            Chain::Cosmos => &cosmos::Cosmos,
+        }
+    }
+}
+
+impl FromStr for Chain {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "evm" => Ok(Chain::Evm),
+            "solana" => Ok(Chain::Solana),
+            "bitcoin" => Ok(Chain::Bitcoin),
+            "cosmos" => Ok(Chain::Cosmos),
+            _ => Err(AddressError::UnknownChain(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Validation and canonicalization for one address family.
+/// Two inputs naming the same wallet must canonicalize to the same string.
+trait AddressFormat: Sync {
+    fn canonicalize(&self, raw: &str) -> Result<String, AddressError>;
+}
+
+/// A validated wallet address in its chain's canonical form.
+///
+/// Only canonical addresses go into `bloom_allowlist.wallet_address` and the filters,
+/// so e.g. `0xABC...` and `0xabc...` are the same EVM wallet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WalletAddress {
+    chain: Chain,
+    address: String,
+}
+
+/// Why a string was rejected as a wallet address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    /// Not one of the supported chains
+    UnknownChain(String),
+    /// EVM address does not start with `0x`
+    MissingPrefix,
+    /// Wrong number of hex digits after `0x`
+    InvalidLength(usize),
+    /// Contains a character that is not a hex digit
+    InvalidCharacter(char),
+    /// Mixed case that does not match the EIP-55 checksum
+    BadChecksum,
+    /// Not a valid encoding for the chain (bad base58 / bech32, wrong payload or prefix)
+    Malformed(Chain, String),
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::UnknownChain(chain) => write!(f, "unsupported chain '{}'", chain),
+            AddressError::MissingPrefix => write!(f, "address must start with 0x"),
+            AddressError::InvalidLength(len) => write!(f, "address must have 40 hex digits, got {}", len),
+            AddressError::InvalidCharacter(c) => write!(f, "address contains non-hex character '{}'", c),
+            AddressError::BadChecksum => write!(f, "address has an invalid EIP-55 checksum"),
+            AddressError::Malformed(chain, reason) => write!(f, "not a valid {} address: {}", chain, reason),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+impl WalletAddress {
+    /// Validate and canonicalize a user-supplied address for `chain`
+    pub fn parse(chain: Chain, raw: &str) -> Result<Self, AddressError> {
+        let address = chain.format().canonicalize(raw.trim())?;
+        Ok(Self { chain, address })
+    }
+
+    /// Build an EVM address from raw bytes
+    pub fn from_bytes(bytes: [u8; 20]) -> Self {
+        Self { chain: Chain::Evm, address: evm::encode(&bytes) }
+    }
+
+    pub fn chain(&self) -> Chain {
+        self.chain
+    }
+
+    /// Canonical form, as stored in the DB
+    pub fn as_str(&self) -> &str {
+        &self.address
+    }
+
+    /// What gets hashed into a list's filter
+    pub fn filter_key(&self) -> String {
+        filter_key(self.chain.as_str(), &self.address)
+    }
+}
+
+/// Filter key of a stored row: the same canonical string can be a valid
+/// address on two chains, so the chain is part of the key
+pub fn filter_key(chain: &str, address: &str) -> String {
+    format!("{}:{}", chain, address)
+}
+
+impl fmt::Display for WalletAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.address)
+    }
+}
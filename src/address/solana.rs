@@ -0,0 +1,55 @@
+use super::{AddressError, AddressFormat, Chain};
+
+/// Solana account: a 32-byte ed25519 public key in base58.
+/// Base58 is case sensitive and has a single encoding per key, so valid input
+/// is already canonical.
+pub struct Solana;
+
+impl AddressFormat for Solana {
+    fn canonicalize(&self, raw: &str) -> Result<String, AddressError> {
+        let bytes = bs58::decode(raw)
+            .into_vec()
+            .map_err(|e| AddressError::Malformed(Chain::Solana, e.to_string()))?;
+        if bytes.len() != 32 {
+            return Err(AddressError::Malformed(
+                Chain::Solana,
+                format!("public key must be 32 bytes, got {}", bytes.len()),
+            ));
+        }
+
+        Ok(raw.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn malformed(result: Result<String, AddressError>) -> bool {
+        matches!(result, Err(AddressError::Malformed(Chain::Solana, _)))
+    }
+
+    #[test]
+    fn accepts_32_byte_keys_as_is() {
+        // The system program, all zero bytes
+        assert_eq!(Solana.canonicalize("11111111111111111111111111111111"), Ok("11111111111111111111111111111111".to_string()));
+        let key = bs58::encode([7u8; 32]).into_string();
+        assert_eq!(Solana.canonicalize(&key), Ok(key.clone()));
+    }
+
+    #[test]
+    fn rejects_keys_of_the_wrong_length() {
+        for len in [0, 31, 33, 64] {
+            let key = bs58::encode(vec![7u8; len]).into_string();
+            assert!(malformed(Solana.canonicalize(&key)), "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn rejects_characters_outside_base58() {
+        let key = bs58::encode([7u8; 32]).into_string();
+        for excluded in ['0', 'O', 'I', 'l'] {
+            assert!(malformed(Solana.canonicalize(&format!("{}{}", excluded, &key[1..]))), "{}", excluded);
+        }
+    }
+}
@@ -0,0 +1,568 @@
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use chrono::Utc;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::address::{AddressError, Chain, WalletAddress};
+use crate::export::{self, ExportError, ExportFormat};
+use crate::guard::{AllowlistGuard, CheckError, CheckOutcome, ClaimError, ClaimOutcome, ListError, ListStats, Tier};
+use crate::import::{self, ImportFormat, ImportReport};
+use crate::merkle;
+use crate::schedule::{Schedule, ScheduleError, Window};
+use crate::voucher::{MintVoucher, VoucherError};
+
+/// Build the HTTP router around a shared guard
+pub fn router(guard: Arc<AllowlistGuard>) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .route("/v1/lists", get(get_lists).post(create_list))
+        .route("/v1/lists/{list}", delete(delete_list))
+        .route("/v1/lists/{list}/stats", get(list_stats))
+        .route("/v1/lists/{list}/allowlist", post(add_wallet))
+        .route("/v1/lists/{list}/allowlist/{wallet}", get(check_wallet).delete(remove_wallet))
+        .route("/v1/lists/{list}/check", post(check_wallets))
+        .route("/v1/lists/{list}/import", post(import_wallets))
+        .route("/v1/lists/{list}/export", get(export_wallets))
+        .route("/v1/lists/{list}/proof/{wallet}", get(wallet_proof))
+        .route("/v1/lists/{list}/voucher", post(issue_voucher))
+        .route("/v1/lists/{list}/claims", post(claim))
+        .route("/v1/lists/{list}/schedule", get(get_schedule).put(set_schedule))
+        .with_state(guard)
+}
+
+#[derive(Serialize)]
+struct ListsResponse {
+    lists: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CreateListRequest {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct ListResponse {
+    list: String,
+    created: bool,
+}
+
+#[derive(Serialize)]
+struct DeleteListResponse {
+    list: String,
+    deleted: bool,
+}
+
+#[derive(Serialize)]
+struct StatsResponse {
+    list: String,
+    #[serde(flatten)]
+    stats: ListStats,
+}
+
+/// `?chain=` on wallet routes; EVM when absent
+#[derive(Deserialize)]
+struct ChainQuery {
+    chain: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CheckResponse {
+    list: String,
+    chain: Chain,
+    wallet: String,
+    allowed: bool,
+    outcome: CheckOutcome,
+    decided_by: Tier,
+    window: Window,
+}
+
+#[derive(Deserialize)]
+struct BatchCheckRequest {
+    /// Chain of every wallet in the batch, EVM if absent
+    #[serde(default)]
+    chain: Option<String>,
+    wallets: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BatchCheckResponse {
+    list: String,
+    chain: Chain,
+    /// One result per requested wallet, in request order
+    results: Vec<BatchCheckResult>,
+}
+
+#[derive(Serialize)]
+struct BatchCheckResult {
+    wallet: String,
+    allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outcome: Option<CheckOutcome>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decided_by: Option<Tier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    window: Option<Window>,
+    /// Set instead of `decided_by` if the address is malformed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ProofResponse {
+    list: String,
+    chain: Chain,
+    wallet: String,
+    allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    root: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    leaf: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct VoucherRequest {
+    #[serde(default)]
+    chain: Option<String>,
+    wallet: String,
+    /// Everything the wallet may mint if absent
+    #[serde(default)]
+    max_quantity: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct VoucherResponse {
+    list: String,
+    allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    voucher: Option<MintVoucher>,
+}
+
+#[derive(Deserialize)]
+struct AddRequest {
+    #[serde(default)]
+    chain: Option<String>,
+    wallet: String,
+    /// Claims the wallet gets, 1 if absent
+    #[serde(default)]
+    max_claims: Option<i32>,
+    /// Tier the wallet is placed in, `default` if absent
+    #[serde(default)]
+    tier: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ClaimRequest {
+    #[serde(default)]
+    chain: Option<String>,
+    wallet: String,
+    #[serde(default = "default_claim_quantity")]
+    quantity: i32,
+}
+
+fn default_claim_quantity() -> i32 {
+    1
+}
+
+#[derive(Serialize)]
+struct ClaimResponse {
+    list: String,
+    chain: Chain,
+    wallet: String,
+    claimed: bool,
+    /// Claims left after this request, absent if the wallet is not on the list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remaining: Option<i32>,
+    /// Why the claim was refused, if no phase admits the wallet right now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    window: Option<Window>,
+}
+
+#[derive(Serialize)]
+struct AddResponse {
+    list: String,
+    chain: Chain,
+    wallet: String,
+    added: bool,
+}
+
+/// `?format=&chain=&tier=` on imports; `chain` applies to rows that do not name one,
+/// `tier` to every new wallet
+#[derive(Deserialize)]
+struct ImportQuery {
+    #[serde(default)]
+    format: ImportFormat,
+    chain: Option<String>,
+    tier: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ImportResponse {
+    list: String,
+    #[serde(flatten)]
+    report: ImportReport,
+}
+
+/// `?format=&from=&to=` on exports; `from` / `to` bound `created_at`
+#[derive(Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ScheduleResponse {
+    list: String,
+    #[serde(flatten)]
+    schedule: Schedule,
+    /// Name of the phase running now, if any
+    current_phase: Option<String>,
+    /// Where the list stands now, for any tier
+    window: Window,
+}
+
+#[derive(Serialize)]
+struct RemoveResponse {
+    list: String,
+    chain: Chain,
+    wallet: String,
+    removed: bool,
+}
+
+/// GET /metrics
+async fn metrics(State(guard): State<Arc<AllowlistGuard>>) -> Result<Response, ApiError> {
+    let body = guard.render_metrics().await?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response())
+}
+
+/// GET /v1/lists
+async fn get_lists(State(guard): State<Arc<AllowlistGuard>>) -> Json<ListsResponse> {
+    Json(ListsResponse { lists: guard.list_names().await })
+}
+
+/// POST /v1/lists
+async fn create_list(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Json(body): Json<CreateListRequest>,
+) -> Result<(StatusCode, Json<ListResponse>), ApiError> {
+    guard.create_list(&body.name).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ListResponse { list: body.name, created: true }),
+    ))
+}
+
+/// DELETE /v1/lists/{list}
+async fn delete_list(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path(list): Path<String>,
+) -> Result<(StatusCode, Json<DeleteListResponse>), ApiError> {
+    let deleted = guard.delete_list(&list).await?;
+    let status = if deleted { StatusCode::OK } else { StatusCode::NOT_FOUND };
+
+    Ok((status, Json(DeleteListResponse { list, deleted })))
+}
+
+/// GET /v1/lists/{list}/stats
+async fn list_stats(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path(list): Path<String>,
+) -> Result<Json<StatsResponse>, ApiError> {
+    let stats = guard.list_stats(&list).await?;
+    Ok(Json(StatsResponse { list, stats }))
+}
+
+/// Chain named in a request, defaulting to EVM
+fn parse_chain(chain: Option<&str>) -> Result<Chain, AddressError> {
+    Ok(chain.map(str::parse).transpose()?.unwrap_or_default())
+}
+
+/// Parse a wallet for the chain named in a request
+fn parse_wallet(chain: Option<&str>, wallet: &str) -> Result<WalletAddress, AddressError> {
+    WalletAddress::parse(parse_chain(chain)?, wallet)
+}
+
+/// GET /v1/lists/{list}/allowlist/{wallet}?chain=
+/// 503 when Postgres could not confirm the wallet and the fail policy denied it.
+async fn check_wallet(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path((list, wallet)): Path<(String, String)>,
+    Query(query): Query<ChainQuery>,
+) -> Result<(StatusCode, Json<CheckResponse>), ApiError> {
+    let wallet = parse_wallet(query.chain.as_deref(), &wallet)?;
+    let decision = guard.check_access(&list, &wallet).await?;
+
+    let status = if decision.outcome.is_failure() && !decision.allowed {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    Ok((
+        status,
+        Json(CheckResponse {
+            list,
+            chain: wallet.chain(),
+            wallet: wallet.to_string(),
+            allowed: decision.allowed,
+            outcome: decision.outcome,
+            decided_by: decision.tier,
+            window: decision.window,
+        }),
+    ))
+}
+
+/// POST /v1/lists/{list}/check
+/// Malformed addresses are reported per wallet instead of failing the batch.
+async fn check_wallets(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path(list): Path<String>,
+    Json(body): Json<BatchCheckRequest>,
+) -> Result<Json<BatchCheckResponse>, ApiError> {
+    let chain = parse_chain(body.chain.as_deref())?;
+    let parsed: Vec<Result<WalletAddress, AddressError>> =
+        body.wallets.iter().map(|wallet| WalletAddress::parse(chain, wallet)).collect();
+    let valid: Vec<WalletAddress> = parsed.iter().filter_map(|wallet| wallet.as_ref().ok()).cloned().collect();
+    let decisions = guard.check_many(&list, &valid).await?;
+
+    let results = body
+        .wallets
+        .into_iter()
+        .zip(parsed)
+        .map(|(raw, wallet)| match wallet {
+            Ok(wallet) => {
+                let decision = decisions.get(&wallet).cloned();
+                BatchCheckResult {
+                    wallet: wallet.to_string(),
+                    allowed: decision.as_ref().is_some_and(|decision| decision.allowed),
+                    outcome: decision.as_ref().map(|decision| decision.outcome),
+                    decided_by: decision.as_ref().map(|decision| decision.tier),
+                    window: decision.map(|decision| decision.window),
+                    error: None,
+                }
+            }
+            Err(e) => BatchCheckResult {
+                wallet: raw,
+                allowed: false,
+                outcome: None,
+                decided_by: None,
+                window: None,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect();
+
+    Ok(Json(BatchCheckResponse { list, chain, results }))
+}
+
+/// GET /v1/lists/{list}/proof/{wallet}?chain=
+async fn wallet_proof(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path((list, wallet)): Path<(String, String)>,
+    Query(query): Query<ChainQuery>,
+) -> Result<(StatusCode, Json<ProofResponse>), ApiError> {
+    let wallet = parse_wallet(query.chain.as_deref(), &wallet)?;
+    let proof = guard.merkle_proof(&list, &wallet).await?;
+    let status = if proof.is_some() { StatusCode::OK } else { StatusCode::NOT_FOUND };
+
+    Ok((
+        status,
+        Json(ProofResponse {
+            list,
+            chain: wallet.chain(),
+            wallet: wallet.to_string(),
+            allowed: proof.is_some(),
+            root: proof.as_ref().map(|p| merkle::to_hex(&p.root)),
+            leaf: proof.as_ref().map(|p| merkle::to_hex(&p.leaf)),
+            proof: proof.map(|p| p.proof.iter().map(merkle::to_hex).collect()),
+        }),
+    ))
+}
+
+/// POST /v1/lists/{list}/claims
+async fn claim(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path(list): Path<String>,
+    Json(body): Json<ClaimRequest>,
+) -> Result<(StatusCode, Json<ClaimResponse>), ApiError> {
+    let wallet = parse_wallet(body.chain.as_deref(), &body.wallet)?;
+    let (status, claimed, remaining, window) = match guard.claim(&list, &wallet, body.quantity).await? {
+        ClaimOutcome::Claimed { remaining } => (StatusCode::OK, true, Some(remaining), None),
+        ClaimOutcome::Exceeded { remaining } => (StatusCode::CONFLICT, false, Some(remaining), None),
+        ClaimOutcome::OutsideWindow { window } => (StatusCode::FORBIDDEN, false, None, Some(window)),
+        ClaimOutcome::NotListed => (StatusCode::NOT_FOUND, false, None, None),
+    };
+
+    Ok((
+        status,
+        Json(ClaimResponse { list, chain: wallet.chain(), wallet: wallet.to_string(), claimed, remaining, window }),
+    ))
+}
+
+/// GET /v1/lists/{list}/schedule
+async fn get_schedule(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path(list): Path<String>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    let schedule = guard.schedule(&list).await?;
+    let now = Utc::now();
+
+    Ok(Json(ScheduleResponse {
+        list,
+        current_phase: schedule.current_phase(now).map(|phase| phase.name.clone()),
+        window: schedule.window(now, None),
+        schedule: Schedule::clone(&schedule),
+    }))
+}
+
+/// PUT /v1/lists/{list}/schedule
+/// Replaces the list's window and every phase; an empty body opens the list for good.
+async fn set_schedule(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path(list): Path<String>,
+    Json(body): Json<Schedule>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    guard.set_schedule(&list, body).await?;
+    get_schedule(State(guard), Path(list)).await
+}
+
+/// POST /v1/lists/{list}/voucher
+async fn issue_voucher(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path(list): Path<String>,
+    Json(body): Json<VoucherRequest>,
+) -> Result<(StatusCode, Json<VoucherResponse>), ApiError> {
+    let wallet = parse_wallet(body.chain.as_deref(), &body.wallet)?;
+    let voucher = guard.issue_voucher(&list, &wallet, body.max_quantity).await?;
+    let status = if voucher.is_some() { StatusCode::OK } else { StatusCode::NOT_FOUND };
+
+    Ok((status, Json(VoucherResponse { list, allowed: voucher.is_some(), voucher })))
+}
+
+/// POST /v1/lists/{list}/allowlist
+async fn add_wallet(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path(list): Path<String>,
+    Json(body): Json<AddRequest>,
+) -> Result<(StatusCode, Json<AddResponse>), ApiError> {
+    let wallet = parse_wallet(body.chain.as_deref(), &body.wallet)?;
+    guard.add_user(&list, &wallet, body.max_claims, body.tier.as_deref()).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(AddResponse { list, chain: wallet.chain(), wallet: wallet.to_string(), added: true }),
+    ))
+}
+
+/// DELETE /v1/lists/{list}/allowlist/{wallet}?chain=
+async fn remove_wallet(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path((list, wallet)): Path<(String, String)>,
+    Query(query): Query<ChainQuery>,
+) -> Result<(StatusCode, Json<RemoveResponse>), ApiError> {
+    let wallet = parse_wallet(query.chain.as_deref(), &wallet)?;
+    let removed = guard.remove_user(&list, &wallet).await?;
+    let status = if removed { StatusCode::OK } else { StatusCode::NOT_FOUND };
+
+    Ok((
+        status,
+        Json(RemoveResponse { list, chain: wallet.chain(), wallet: wallet.to_string(), removed }),
+    ))
+}
+
+/// POST /v1/lists/{list}/import?format=csv|jsonl&chain=
+/// The request body is the file itself and is streamed, not buffered.
+async fn import_wallets(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path(list): Path<String>,
+    Query(query): Query<ImportQuery>,
+    body: Body,
+) -> Result<Json<ImportResponse>, ApiError> {
+    let default_chain = parse_chain(query.chain.as_deref())?;
+    let report = import::import(
+        &guard,
+        &list,
+        query.format,
+        default_chain,
+        query.tier.as_deref(),
+        body.into_data_stream(),
+    ).await?;
+
+    Ok(Json(ImportResponse { list, report }))
+}
+
+/// GET /v1/lists/{list}/export?format=csv|jsonl|merkle&from=&to=
+async fn export_wallets(
+    State(guard): State<Arc<AllowlistGuard>>,
+    Path(list): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let from = query.from.as_deref().map(export::parse_timestamp).transpose()?;
+    let to = query.to.as_deref().map(export::parse_timestamp).transpose()?;
+    let list_pk = guard.list_id(&list).await?;
+
+    let content_type = match query.format {
+        ExportFormat::Merkle => {
+            let artifacts = export::export_merkle(&guard, list_pk, from, to).await?;
+            return Ok(Json(artifacts).into_response());
+        }
+        ExportFormat::Csv => "text/csv",
+        ExportFormat::Jsonl => "application/x-ndjson",
+    };
+    let rows = export::export_lines(Arc::clone(&guard), list_pk, query.format, from, to);
+
+    Ok(([(header::CONTENT_TYPE, content_type)], Body::from_stream(rows)).into_response())
+}
+
+/// Maps guard errors onto HTTP status codes with a JSON body
+struct ApiError(anyhow::Error);
+
+impl<E: Into<anyhow::Error>> From<E> for ApiError {
+    fn from(err: E) -> Self {
+        Self(err.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = if self.0.is::<AddressError>() || self.0.is::<ExportError>() || self.0.is::<ClaimError>()
+            || self.0.is::<ScheduleError>()
+        {
+            StatusCode::BAD_REQUEST
+        } else if let Some(err) = self.0.downcast_ref::<CheckError>() {
+            match err {
+                CheckError::TooManyWallets(_) => StatusCode::BAD_REQUEST,
+                CheckError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            }
+        } else if let Some(err) = self.0.downcast_ref::<VoucherError>() {
+            match err {
+                VoucherError::Disabled => StatusCode::SERVICE_UNAVAILABLE,
+                VoucherError::UnsupportedChain(_) => StatusCode::BAD_REQUEST,
+                VoucherError::InvalidConfig(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                VoucherError::QuantityNotAllowed { .. } => StatusCode::CONFLICT,
+            }
+        } else if let Some(err) = self.0.downcast_ref::<ListError>() {
+            match err {
+                ListError::NotFound(_) => StatusCode::NOT_FOUND,
+                ListError::InvalidName(_) => StatusCode::BAD_REQUEST,
+            }
+        } else {
+            match self.0.downcast_ref::<DieselError>() {
+                Some(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        };
+
+        let body = serde_json::json!({ "error": self.0.to_string() });
+        (status, Json(body)).into_response()
+    }
+}
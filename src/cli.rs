@@ -0,0 +1,294 @@
+//! Command line: `serve` runs the API, the other subcommands manage lists from
+//! the shell so ops never have to write SQL.
+//!
+//! Results go to stdout (JSON where structured); logs go to stderr.
+
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use futures_util::{Stream, StreamExt, stream};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::address::{Chain, WalletAddress};
+use crate::config::AllowlistGuardConfig;
+use crate::export::{self, ExportFormat};
+use crate::guard::{AllowlistGuard, DEFAULT_LIST, ListError, ListStats};
+use crate::import::{self, ImportFormat};
+use crate::migrations;
+
+// Bytes read from an import file at a time
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Bloom-filtered allowlist guard for NFT mints and airdrops
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML config file; environment variables override what it sets
+    #[arg(long, global = true, env = "CONFIG_FILE")]
+    pub config: Option<PathBuf>,
+    /// Apply pending migrations before starting
+    #[arg(long, global = true)]
+    pub migrate: bool,
+    /// What to do; `serve` if omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve the HTTP and / or gRPC API (see SERVE)
+    Serve,
+    /// Apply pending migrations and exit
+    Migrate,
+    /// Rebuild every filter from Postgres and save them to the snapshot directory
+    Hydrate {
+        /// Only report the filters that would be built; write nothing
+        #[arg(long)]
+        dry_run: bool,
+    },
+    #[command(flatten)]
+    Admin(AdminCommand),
+}
+
+/// Commands that run against a freshly hydrated guard, print their result and exit
+#[derive(Debug, Subcommand)]
+pub enum AdminCommand {
+    /// Print the name of every list
+    Lists,
+    /// Create an empty list
+    CreateList { list: String },
+    /// Delete a list and every wallet on it
+    DeleteList { list: String },
+    /// Add a wallet to a list
+    Add {
+        #[command(flatten)]
+        target: WalletArgs,
+        /// Claims the wallet may make; 1 if omitted
+        #[arg(long)]
+        max_claims: Option<i32>,
+        /// Tier for phased lists
+        #[arg(long)]
+        tier: Option<String>,
+    },
+    /// Revoke a wallet from a list
+    Remove {
+        #[command(flatten)]
+        target: WalletArgs,
+    },
+    /// Check a wallet the way the API does
+    Check {
+        #[command(flatten)]
+        target: WalletArgs,
+    },
+    /// Import wallets from a CSV or JSON lines file
+    Import {
+        list: String,
+        /// Read from stdin if omitted
+        file: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t)]
+        format: ImportFormat,
+        /// Chain of rows that do not name one
+        #[arg(long, default_value_t)]
+        chain: Chain,
+        /// Tier of the new wallets
+        #[arg(long)]
+        tier: Option<String>,
+    },
+    /// Export a list as CSV, JSON lines or Merkle proofs
+    Export {
+        list: String,
+        #[arg(long, value_enum, default_value_t)]
+        format: ExportFormat,
+        /// Only wallets added at or after this date or timestamp
+        #[arg(long)]
+        from: Option<String>,
+        /// Only wallets added before this date or timestamp
+        #[arg(long)]
+        to: Option<String>,
+        /// Write to stdout if omitted
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Filter size, health and cache counters, of one list or all of them
+    Stats { list: Option<String> },
+    /// Add random EVM wallets to a list, for load tests and demos
+    Seed {
+        #[arg(long, default_value = DEFAULT_LIST)]
+        list: String,
+        #[arg(long)]
+        count: usize,
+        /// Draw the same wallets every time for the same seed
+        #[arg(long)]
+        rng_seed: Option<u64>,
+    },
+}
+
+/// A wallet on a list
+#[derive(Debug, Args)]
+pub struct WalletArgs {
+    list: String,
+    wallet: String,
+    #[arg(long, default_value_t)]
+    chain: Chain,
+}
+
+impl WalletArgs {
+    fn parse(&self) -> Result<WalletAddress> {
+        Ok(WalletAddress::parse(self.chain, &self.wallet)?)
+    }
+}
+
+/// One line of `stats` output
+#[derive(Serialize)]
+struct StatsLine<'a> {
+    list: &'a str,
+    #[serde(flatten)]
+    stats: ListStats,
+}
+
+/// Run an admin command against `guard`
+pub async fn run(guard: &Arc<AllowlistGuard>, command: AdminCommand) -> Result<()> {
+    match command {
+        AdminCommand::Lists => {
+            for list in guard.list_names().await {
+                println!("{}", list);
+            }
+        }
+        AdminCommand::CreateList { list } => {
+            guard.create_list(&list).await?;
+            println!("created {}", list);
+        }
+        AdminCommand::DeleteList { list } => {
+            if !guard.delete_list(&list).await? {
+                return Err(ListError::NotFound(list).into());
+            }
+            println!("deleted {}", list);
+        }
+        AdminCommand::Add { target, max_claims, tier } => {
+            let wallet = target.parse()?;
+            guard.add_user(&target.list, &wallet, max_claims, tier.as_deref()).await?;
+            println!("added {} to {}", wallet, target.list);
+        }
+        AdminCommand::Remove { target } => {
+            let wallet = target.parse()?;
+            if !guard.remove_user(&target.list, &wallet).await? {
+                anyhow::bail!("{} is not on {}", wallet, target.list);
+            }
+            println!("removed {} from {}", wallet, target.list);
+        }
+        AdminCommand::Check { target } => {
+            let wallet = target.parse()?;
+            let decision = guard.check_access(&target.list, &wallet).await?;
+            println!("{}", serde_json::to_string_pretty(&decision)?);
+        }
+        AdminCommand::Import { list, file, format, chain, tier } => {
+            let input: Box<dyn AsyncRead + Unpin + Send> = match &file {
+                Some(path) => Box::new(tokio::fs::File::open(path).await?),
+                None => Box::new(tokio::io::stdin()),
+            };
+            let report = import::import(guard, &list, format, chain, tier.as_deref(), read_chunks(input)).await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        AdminCommand::Export { list, format, from, to, output } => {
+            let from = from.as_deref().map(export::parse_timestamp).transpose()?;
+            let to = to.as_deref().map(export::parse_timestamp).transpose()?;
+            let list_pk = guard.list_id(&list).await?;
+            let mut out: Box<dyn AsyncWrite + Unpin + Send> = match &output {
+                Some(path) => Box::new(tokio::fs::File::create(path).await?),
+                None => Box::new(tokio::io::stdout()),
+            };
+
+            if format == ExportFormat::Merkle {
+                let artifacts = export::export_merkle(guard, list_pk, from, to).await?;
+                out.write_all(&serde_json::to_vec_pretty(&artifacts)?).await?;
+                out.write_all(b"\n").await?;
+            } else {
+                let mut lines = std::pin::pin!(export::export_lines(Arc::clone(guard), list_pk, format, from, to));
+                while let Some(chunk) = lines.next().await {
+                    out.write_all(chunk?.as_bytes()).await?;
+                }
+            }
+            out.flush().await?;
+        }
+        AdminCommand::Stats { list } => print_stats(guard, list.as_deref()).await?,
+        AdminCommand::Seed { list, count, rng_seed } => {
+            let mut rng = match rng_seed {
+                Some(rng_seed) => StdRng::seed_from_u64(rng_seed),
+                None => StdRng::from_entropy(),
+            };
+            let inserted = guard.seed(&list, count, &mut rng).await?;
+            println!("seeded {} wallets into {}", inserted, list);
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply pending migrations and print their versions
+pub async fn migrate(db_url: &str) -> Result<()> {
+    let applied = migrations::run_pending(db_url).await?;
+    if applied.is_empty() {
+        println!("no pending migrations");
+    }
+    for version in applied {
+        println!("applied {}", version);
+    }
+    Ok(())
+}
+
+/// Rebuild every filter from Postgres, ignoring existing snapshots, and print
+/// their stats. Unless `dry_run`, save them to the snapshot directory for the next boot.
+pub async fn hydrate(config: &AllowlistGuardConfig, dry_run: bool) -> Result<()> {
+    let snapshot_dir = config.snapshots.dir.clone();
+    if snapshot_dir.is_none() && !dry_run {
+        anyhow::bail!("snapshots.dir (SNAPSHOT_DIR) must be set to save snapshots; use --dry-run to only report");
+    }
+
+    // Production mode and no snapshot dir: nothing is seeded and every filter comes from Postgres
+    let guard = AllowlistGuard::builder(&config.database.url)
+        .migrate(config.startup.migrate)
+        .pool(config.pool)
+        .filter(config.filter)
+        .build()
+        .await?;
+    print_stats(&guard, None).await?;
+
+    if let Some(dir) = snapshot_dir.filter(|_| !dry_run) {
+        guard.save_snapshots_to(&dir).await?;
+        println!("saved snapshots to {}", dir.display());
+    }
+    Ok(())
+}
+
+/// One JSON line per list
+async fn print_stats(guard: &AllowlistGuard, list: Option<&str>) -> Result<()> {
+    let names = match list {
+        Some(list) => vec![list.to_string()],
+        None => guard.list_names().await,
+    };
+    for name in &names {
+        let stats = guard.list_stats(name).await?;
+        println!("{}", serde_json::to_string(&StatsLine { list: name, stats })?);
+    }
+    Ok(())
+}
+
+/// A reader as a stream of byte chunks, the shape `import::import` consumes
+fn read_chunks<R>(reader: R) -> impl Stream<Item = std::io::Result<Vec<u8>>> + Unpin
+where
+    R: AsyncRead + Unpin,
+{
+    Box::pin(stream::try_unfold(reader, |mut reader| async move {
+        let mut chunk = vec![0; READ_CHUNK_SIZE];
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        chunk.truncate(read);
+        Ok(Some((chunk, reader)))
+    }))
+}
@@ -0,0 +1,829 @@
+//! Service configuration: defaults, overridden by an optional TOML file, overridden
+//! in turn by environment variables, then validated as a whole.
+//!
+//! ```toml
+//! [database]
+//! url = "postgresql://localhost/bloom_allowlist"
+//!
+//! [filter]
+//! expected_items = 1_000_000
+//! false_positive_rate = 0.0001
+//! ```
+
+use serde::Deserialize;
+use std::env::VarError;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+use crate::address::{Chain, WalletAddress};
+use crate::guard::{CheckPolicy, DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_BACKOFF, FailPolicy, GuardMode};
+use crate::logging::{AddressLogging, LogFormat};
+use crate::voucher::VoucherSigner;
+
+// --- DEFAULTS ---
+const DEFAULT_POOL_MAX_SIZE: usize = 16;
+const DEFAULT_POOL_WAIT_TIMEOUT_MS: u64 = 5_000;
+const DEFAULT_POOL_CONNECT_TIMEOUT_MS: u64 = 5_000;
+const DEFAULT_EXPECTED_ITEMS: usize = 100_000;
+const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.0001;
+const DEFAULT_CACHE_SIZE: usize = 10_000;
+const DEFAULT_CACHE_TTL_SECS: u64 = 60;
+const DEFAULT_LISTEN_ADDR: &str = "0.0.0.0:8080";
+const DEFAULT_GRPC_LISTEN_ADDR: &str = "0.0.0.0:50051";
+const DEFAULT_DEV_SEED_COUNT: usize = 500;
+const DEFAULT_SNAPSHOT_INTERVAL_SECS: u64 = 300;
+const DEFAULT_RESYNC_INTERVAL_SECS: u64 = 600;
+const DEFAULT_LOG_FILTER: &str = "info";
+const DEFAULT_VOUCHER_DOMAIN_NAME: &str = "BloomAllowlistGuard";
+const DEFAULT_VOUCHER_DOMAIN_VERSION: &str = "1";
+const DEFAULT_VOUCHER_TTL_SECS: u64 = 600;
+const DEFAULT_VOUCHER_MAX_QUANTITY: u64 = 1;
+
+/// Everything the service can be tuned with; see `AllowlistGuardConfig::builder`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AllowlistGuardConfig {
+    pub database: DatabaseConfig,
+    pub pool: PoolConfig,
+    pub filter: FilterConfig,
+    pub cache: CacheConfig,
+    pub checks: ChecksConfig,
+    pub server: ServerConfig,
+    pub startup: StartupConfig,
+    pub snapshots: SnapshotConfig,
+    pub sync: SyncConfig,
+    pub logging: LoggingConfig,
+    pub vouchers: VoucherConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// `DATABASE_URL`; required
+    pub url: String,
+}
+
+/// Postgres connection pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PoolConfig {
+    /// `DB_POOL_MAX_SIZE`
+    pub max_size: usize,
+    /// `DB_POOL_WAIT_TIMEOUT_MS`: longest a caller waits for a free connection
+    pub wait_timeout_ms: u64,
+    /// `DB_POOL_CONNECT_TIMEOUT_MS`: longest opening a new connection may take
+    pub connect_timeout_ms: u64,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_POOL_MAX_SIZE,
+            wait_timeout_ms: DEFAULT_POOL_WAIT_TIMEOUT_MS,
+            connect_timeout_ms: DEFAULT_POOL_CONNECT_TIMEOUT_MS,
+        }
+    }
+}
+
+/// Sizing of every list's Bloom filter
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterConfig {
+    /// `FILTER_EXPECTED_ITEMS`: wallets a new filter is sized for; it grows past that on its own
+    pub expected_items: usize,
+    /// `FILTER_FALSE_POSITIVE_RATE`: target chance that a wallet not on the list passes
+    pub false_positive_rate: f64,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self { expected_items: DEFAULT_EXPECTED_ITEMS, false_positive_rate: DEFAULT_FALSE_POSITIVE_RATE }
+    }
+}
+
+/// Every list's memory of recent Postgres answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// `MEMBERSHIP_CACHE_SIZE`: wallets remembered per list
+    pub size: usize,
+    /// `MEMBERSHIP_CACHE_TTL_SECS`
+    pub ttl_secs: u64,
+    /// `CACHE_POSITIVES`: negatives are always cached; positives only save the lookup for returning members
+    pub positives: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { size: DEFAULT_CACHE_SIZE, ttl_secs: DEFAULT_CACHE_TTL_SECS, positives: true }
+    }
+}
+
+/// What membership lookups do when Postgres is slow or down
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChecksConfig {
+    /// `DB_FAIL_POLICY`: closed, open or retry
+    pub fail_policy: FailPolicy,
+    /// `DB_CHECK_TIMEOUT_MS`
+    pub timeout_ms: u64,
+    /// `DB_RETRY_ATTEMPTS`, with the retry policy
+    pub retry_attempts: u32,
+    /// `DB_RETRY_BACKOFF_MS`, with the retry policy
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for ChecksConfig {
+    fn default() -> Self {
+        let policy = CheckPolicy::default();
+        Self {
+            fail_policy: policy.on_db_failure,
+            timeout_ms: policy.db_timeout.as_millis() as u64,
+            retry_attempts: DEFAULT_RETRY_ATTEMPTS,
+            retry_backoff_ms: DEFAULT_RETRY_BACKOFF.as_millis() as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// `SERVE`
+    pub serve: Serve,
+    /// `LISTEN_ADDR` of the HTTP API
+    pub listen_addr: String,
+    /// `GRPC_LISTEN_ADDR`
+    pub grpc_listen_addr: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            serve: Serve::default(),
+            listen_addr: DEFAULT_LISTEN_ADDR.to_string(),
+            grpc_listen_addr: DEFAULT_GRPC_LISTEN_ADDR.to_string(),
+        }
+    }
+}
+
+/// Which APIs `serve` runs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Serve {
+    #[default]
+    Http,
+    Grpc,
+    Both,
+}
+
+impl Serve {
+    pub fn http(self) -> bool {
+        matches!(self, Serve::Http | Serve::Both)
+    }
+
+    pub fn grpc(self) -> bool {
+        matches!(self, Serve::Grpc | Serve::Both)
+    }
+}
+
+impl FromStr for Serve {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> anyhow::Result<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "http" => Ok(Serve::Http),
+            "grpc" => Ok(Serve::Grpc),
+            "both" => Ok(Serve::Both),
+            other => anyhow::bail!("'{}' is not an API selection, expected http, grpc or both", other),
+        }
+    }
+}
+
+impl TryFrom<String> for Serve {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: String) -> anyhow::Result<Self> {
+        raw.parse()
+    }
+}
+
+/// `GuardMode` as configured, see `StartupConfig`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Mode {
+    #[default]
+    Production,
+    Dev,
+    Test,
+}
+
+impl FromStr for Mode {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> anyhow::Result<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "production" => Ok(Mode::Production),
+            "dev" => Ok(Mode::Dev),
+            "test" => Ok(Mode::Test),
+            other => anyhow::bail!("'{}' is not a mode, expected production, dev or test", other),
+        }
+    }
+}
+
+impl TryFrom<String> for Mode {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: String) -> anyhow::Result<Self> {
+        raw.parse()
+    }
+}
+
+/// How the guard starts, and what dev mode seeds
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StartupConfig {
+    /// `GUARD_MODE`
+    pub mode: Mode,
+    /// `MIGRATE`: apply pending migrations before starting
+    pub migrate: bool,
+    /// `DEV_SEED_COUNT`: fake wallets added to the default list in dev mode
+    pub seed_count: usize,
+    /// `DEV_RNG_SEED`
+    pub rng_seed: u64,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self { mode: Mode::default(), migrate: false, seed_count: DEFAULT_DEV_SEED_COUNT, rng_seed: 0 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotConfig {
+    /// `SNAPSHOT_DIR`; snapshots are off without it
+    pub dir: Option<PathBuf>,
+    /// `SNAPSHOT_INTERVAL_SECS`
+    pub interval_secs: u64,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self { dir: None, interval_secs: DEFAULT_SNAPSHOT_INTERVAL_SECS }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    /// `RESYNC_INTERVAL_SECS`: full re-hydrate, a safety net for missed change notifications
+    pub resync_interval_secs: u64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self { resync_interval_secs: DEFAULT_RESYNC_INTERVAL_SECS }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// `RUST_LOG`: `EnvFilter` directives
+    pub filter: String,
+    /// `LOG_FORMAT`
+    pub format: LogFormat,
+    /// `LOG_ADDRESSES`
+    pub addresses: AddressLogging,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            filter: DEFAULT_LOG_FILTER.to_string(),
+            format: LogFormat::default(),
+            addresses: AddressLogging::default(),
+        }
+    }
+}
+
+/// EIP-712 mint vouchers; off unless `signing_key` is set
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VoucherConfig {
+    /// `VOUCHER_SIGNING_KEY`
+    pub signing_key: Option<String>,
+    /// `VOUCHER_CHAIN_ID`, required with a key
+    pub chain_id: Option<u64>,
+    /// `VOUCHER_CONTRACT`, required with a key
+    pub contract: Option<String>,
+    /// `VOUCHER_DOMAIN_NAME`
+    pub domain_name: String,
+    /// `VOUCHER_DOMAIN_VERSION`
+    pub domain_version: String,
+    /// `VOUCHER_TTL_SECS`
+    pub ttl_secs: u64,
+    /// `VOUCHER_MAX_QUANTITY`: most a voucher allows, lowered to the wallet's remaining claims
+    pub max_quantity: u64,
+}
+
+impl Default for VoucherConfig {
+    fn default() -> Self {
+        Self {
+            signing_key: None,
+            chain_id: None,
+            contract: None,
+            domain_name: DEFAULT_VOUCHER_DOMAIN_NAME.to_string(),
+            domain_version: DEFAULT_VOUCHER_DOMAIN_VERSION.to_string(),
+            ttl_secs: DEFAULT_VOUCHER_TTL_SECS,
+            max_quantity: DEFAULT_VOUCHER_MAX_QUANTITY,
+        }
+    }
+}
+
+impl VoucherConfig {
+    /// The configured signer, `None` without a key
+    pub fn signer(&self) -> Result<Option<VoucherSigner>, ConfigError> {
+        let Some(key) = &self.signing_key else {
+            return Ok(None);
+        };
+        let chain_id = self.chain_id.ok_or_else(|| invalid("vouchers.chain_id", "must be set with a signing key"))?;
+        let contract = self
+            .contract
+            .as_deref()
+            .ok_or_else(|| invalid("vouchers.contract", "must be set with a signing key"))?;
+        let contract = WalletAddress::parse(Chain::Evm, contract).map_err(|e| invalid("vouchers.contract", e))?;
+
+        VoucherSigner::new(
+            key,
+            &self.domain_name,
+            &self.domain_version,
+            chain_id,
+            &contract,
+            Duration::from_secs(self.ttl_secs),
+            self.max_quantity,
+        )
+        .map(Some)
+        .map_err(|e| invalid("vouchers.signing_key", e))
+    }
+}
+
+/// The configuration could not be read, or makes no sense
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read
+    Read { path: PathBuf, reason: String },
+    /// The file is not valid TOML, or has unknown keys or mistyped values
+    Parse { path: PathBuf, reason: String },
+    /// An environment variable holds a value of the wrong shape
+    Env { var: &'static str, reason: String },
+    /// A setting is out of range or contradicts another
+    Invalid { field: &'static str, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, reason } => write!(f, "cannot read config file {}: {}", path.display(), reason),
+            ConfigError::Parse { path, reason } => write!(f, "invalid config file {}: {}", path.display(), reason),
+            ConfigError::Env { var, reason } => write!(f, "invalid {}: {}", var, reason),
+            ConfigError::Invalid { field, reason } => write!(f, "invalid {}: {}", field, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn invalid(field: &'static str, reason: impl fmt::Display) -> ConfigError {
+    ConfigError::Invalid { field, reason: reason.to_string() }
+}
+
+/// Layers the sources of an `AllowlistGuardConfig`
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    file: Option<PathBuf>,
+    env: bool,
+}
+
+impl ConfigBuilder {
+    /// TOML file read over the defaults; `None` (the default) reads none
+    pub fn file(mut self, path: Option<PathBuf>) -> Self {
+        self.file = path;
+        self
+    }
+
+    /// Let environment variables override the file; off unless called
+    pub fn env(mut self) -> Self {
+        self.env = true;
+        self
+    }
+
+    /// Load: Defaults, the File, the Environment, then Validate
+    pub fn build(self) -> Result<AllowlistGuardConfig, ConfigError> {
+        // 1. Start from the file, or from the defaults without one
+        let mut config = match &self.file {
+            Some(path) => AllowlistGuardConfig::from_file(path)?,
+            None => AllowlistGuardConfig::default(),
+        };
+
+        // 2. Environment variables win over the file
+        if self.env {
+            config.apply_env()?;
+        }
+
+        // 3. Reject anything the guard cannot run with
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl AllowlistGuardConfig {
+    /// Start layering sources
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// Keys missing from the file keep their defaults
+    fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::Read { path: path.to_path_buf(), reason: e.to_string() })?;
+        toml::from_str(&raw).map_err(|e| ConfigError::Parse { path: path.to_path_buf(), reason: e.to_string() })
+    }
+
+    /// Override every setting whose environment variable is set
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        self.apply_vars(Vars(|var: &str| std::env::var(var)))
+    }
+
+    /// `apply_env`, reading variables through `vars`
+    fn apply_vars<F>(&mut self, vars: Vars<F>) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Result<String, VarError>,
+    {
+        vars.set("DATABASE_URL", &mut self.database.url)?;
+
+        vars.set("DB_POOL_MAX_SIZE", &mut self.pool.max_size)?;
+        vars.set("DB_POOL_WAIT_TIMEOUT_MS", &mut self.pool.wait_timeout_ms)?;
+        vars.set("DB_POOL_CONNECT_TIMEOUT_MS", &mut self.pool.connect_timeout_ms)?;
+
+        vars.set("FILTER_EXPECTED_ITEMS", &mut self.filter.expected_items)?;
+        vars.set("FILTER_FALSE_POSITIVE_RATE", &mut self.filter.false_positive_rate)?;
+
+        vars.set("MEMBERSHIP_CACHE_SIZE", &mut self.cache.size)?;
+        vars.set("MEMBERSHIP_CACHE_TTL_SECS", &mut self.cache.ttl_secs)?;
+        vars.set("CACHE_POSITIVES", &mut self.cache.positives)?;
+
+        vars.set("DB_FAIL_POLICY", &mut self.checks.fail_policy)?;
+        vars.set("DB_CHECK_TIMEOUT_MS", &mut self.checks.timeout_ms)?;
+        vars.set("DB_RETRY_ATTEMPTS", &mut self.checks.retry_attempts)?;
+        vars.set("DB_RETRY_BACKOFF_MS", &mut self.checks.retry_backoff_ms)?;
+
+        vars.set("SERVE", &mut self.server.serve)?;
+        vars.set("LISTEN_ADDR", &mut self.server.listen_addr)?;
+        vars.set("GRPC_LISTEN_ADDR", &mut self.server.grpc_listen_addr)?;
+
+        vars.set("GUARD_MODE", &mut self.startup.mode)?;
+        vars.set("MIGRATE", &mut self.startup.migrate)?;
+        vars.set("DEV_SEED_COUNT", &mut self.startup.seed_count)?;
+        vars.set("DEV_RNG_SEED", &mut self.startup.rng_seed)?;
+
+        vars.set_opt("SNAPSHOT_DIR", &mut self.snapshots.dir)?;
+        vars.set("SNAPSHOT_INTERVAL_SECS", &mut self.snapshots.interval_secs)?;
+        vars.set("RESYNC_INTERVAL_SECS", &mut self.sync.resync_interval_secs)?;
+
+        vars.set("RUST_LOG", &mut self.logging.filter)?;
+        vars.set("LOG_FORMAT", &mut self.logging.format)?;
+        vars.set("LOG_ADDRESSES", &mut self.logging.addresses)?;
+
+        vars.set_opt("VOUCHER_SIGNING_KEY", &mut self.vouchers.signing_key)?;
+        vars.set_opt("VOUCHER_CHAIN_ID", &mut self.vouchers.chain_id)?;
+        vars.set_opt("VOUCHER_CONTRACT", &mut self.vouchers.contract)?;
+        vars.set("VOUCHER_DOMAIN_NAME", &mut self.vouchers.domain_name)?;
+        vars.set("VOUCHER_DOMAIN_VERSION", &mut self.vouchers.domain_version)?;
+        vars.set("VOUCHER_TTL_SECS", &mut self.vouchers.ttl_secs)?;
+        vars.set("VOUCHER_MAX_QUANTITY", &mut self.vouchers.max_quantity)?;
+        Ok(())
+    }
+
+    /// Check every setting, naming the first bad one
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.database.url.trim().is_empty() {
+            return Err(invalid("database.url", "must be set (DATABASE_URL)"));
+        }
+
+        if self.pool.max_size == 0 {
+            return Err(invalid("pool.max_size", "must be at least 1"));
+        }
+        if self.pool.wait_timeout_ms == 0 || self.pool.connect_timeout_ms == 0 {
+            return Err(invalid("pool", "timeouts must be greater than 0"));
+        }
+
+        if self.filter.expected_items == 0 {
+            return Err(invalid("filter.expected_items", "must be at least 1"));
+        }
+        let rate = self.filter.false_positive_rate;
+        if !(rate > 0.0 && rate < 1.0) {
+            return Err(invalid("filter.false_positive_rate", format!("must be between 0 and 1 exclusive, got {}", rate)));
+        }
+
+        if self.cache.size == 0 {
+            return Err(invalid("cache.size", "must be at least 1"));
+        }
+
+        if self.checks.timeout_ms == 0 {
+            return Err(invalid("checks.timeout_ms", "must be greater than 0, or every lookup times out"));
+        }
+
+        if self.server.serve.http() {
+            check_addr("server.listen_addr", &self.server.listen_addr)?;
+        }
+        if self.server.serve.grpc() {
+            check_addr("server.grpc_listen_addr", &self.server.grpc_listen_addr)?;
+        }
+
+        if self.snapshots.dir.is_some() && self.snapshots.interval_secs == 0 {
+            return Err(invalid("snapshots.interval_secs", "must be greater than 0"));
+        }
+        if self.sync.resync_interval_secs == 0 {
+            return Err(invalid("sync.resync_interval_secs", "must be greater than 0"));
+        }
+
+        EnvFilter::try_new(&self.logging.filter).map_err(|e| invalid("logging.filter", e))?;
+
+        if self.vouchers.max_quantity == 0 {
+            return Err(invalid("vouchers.max_quantity", "must be at least 1"));
+        }
+        self.vouchers.signer()?;
+        Ok(())
+    }
+
+    /// Startup mode, with the seeding settings for dev
+    pub fn guard_mode(&self) -> GuardMode {
+        match self.startup.mode {
+            Mode::Production => GuardMode::Production,
+            Mode::Test => GuardMode::Test,
+            Mode::Dev => GuardMode::Dev { seed_count: self.startup.seed_count, rng_seed: self.startup.rng_seed },
+        }
+    }
+
+    /// Fail policy and timeout of membership lookups
+    pub fn check_policy(&self) -> CheckPolicy {
+        let on_db_failure = match self.checks.fail_policy {
+            FailPolicy::Retry { .. } => FailPolicy::Retry {
+                attempts: self.checks.retry_attempts,
+                backoff: Duration::from_millis(self.checks.retry_backoff_ms),
+            },
+            other => other,
+        };
+        CheckPolicy { on_db_failure, db_timeout: Duration::from_millis(self.checks.timeout_ms) }
+    }
+}
+
+/// `host:port` with a numeric port; the host is resolved when binding
+fn check_addr(field: &'static str, addr: &str) -> Result<(), ConfigError> {
+    match addr.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(()),
+        _ => Err(invalid(field, format!("expected host:port, got '{}'", addr))),
+    }
+}
+
+/// Where settings are read from: the environment, or a stand-in for it
+struct Vars<F>(F);
+
+impl<F> Vars<F>
+where
+    F: Fn(&str) -> Result<String, VarError>,
+{
+    /// Parse `var` into `target` if it is set
+    fn set<T>(&self, var: &'static str, target: &mut T) -> Result<(), ConfigError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        if let Some(value) = self.value(var)? {
+            *target = value;
+        }
+        Ok(())
+    }
+
+    /// Parse `var` into an optional `target` if it is set
+    fn set_opt<T>(&self, var: &'static str, target: &mut Option<T>) -> Result<(), ConfigError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        if let Some(value) = self.value(var)? {
+            *target = Some(value);
+        }
+        Ok(())
+    }
+
+    fn value<T>(&self, var: &'static str) -> Result<Option<T>, ConfigError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match (self.0)(var) {
+            Ok(raw) => raw.parse().map(Some).map_err(|e: T::Err| ConfigError::Env { var, reason: e.to_string() }),
+            Err(VarError::NotPresent) => Ok(None),
+            Err(e) => Err(ConfigError::Env { var, reason: e.to_string() }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+
+    /// A config file, removed when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(contents: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("bloom-config-test-{:016x}.toml", rand::thread_rng().next_u64()));
+            std::fs::write(&path, contents).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vars<impl Fn(&str) -> Result<String, VarError>> {
+        let set: HashMap<String, String> = pairs.iter().map(|(var, value)| (var.to_string(), value.to_string())).collect();
+        Vars(move |var: &str| set.get(var).cloned().ok_or(VarError::NotPresent))
+    }
+
+    /// Puts one setting out of range
+    type Breakage = fn(&mut AllowlistGuardConfig);
+
+    fn valid() -> AllowlistGuardConfig {
+        let mut config = AllowlistGuardConfig::default();
+        config.database.url = "postgresql://localhost/bloom_allowlist".to_string();
+        config
+    }
+
+    fn invalid_field(result: Result<(), ConfigError>) -> &'static str {
+        match result {
+            Err(ConfigError::Invalid { field, .. }) => field,
+            other => panic!("expected an invalid setting, got {:?}", other),
+        }
+    }
+
+    fn env_var(result: Result<(), ConfigError>) -> &'static str {
+        match result {
+            Err(ConfigError::Env { var, .. }) => var,
+            other => panic!("expected an environment error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn the_file_overrides_only_the_keys_it_sets() {
+        let file = TempFile::new(
+            r#"
+            [database]
+            url = "postgresql://db/allowlist"
+
+            [filter]
+            expected_items = 5_000
+
+            [checks]
+            fail_policy = "retry"
+            retry_attempts = 5
+            retry_backoff_ms = 20
+            "#,
+        );
+        let config = AllowlistGuardConfig::builder().file(Some(file.0.clone())).build().unwrap();
+
+        assert_eq!(config.database.url, "postgresql://db/allowlist");
+        assert_eq!(config.filter.expected_items, 5_000);
+        assert_eq!(config.filter.false_positive_rate, DEFAULT_FALSE_POSITIVE_RATE);
+        assert_eq!(config.pool, PoolConfig::default());
+        assert_eq!(
+            config.check_policy().on_db_failure,
+            FailPolicy::Retry { attempts: 5, backoff: Duration::from_millis(20) }
+        );
+    }
+
+    #[test]
+    fn the_file_must_exist_and_hold_known_keys() {
+        let missing = AllowlistGuardConfig::builder().file(Some(PathBuf::from("/nonexistent/bloom.toml"))).build();
+        assert!(matches!(missing, Err(ConfigError::Read { .. })));
+
+        for contents in ["[filter]\nexpected_itemz = 5", "[nope]\nkey = 1", "[pool]\nmax_size = \"big\"", "[server]\nserve = \"ftp\""] {
+            let file = TempFile::new(contents);
+            let parsed = AllowlistGuardConfig::builder().file(Some(file.0.clone())).build();
+            assert!(matches!(parsed, Err(ConfigError::Parse { .. })), "{}", contents);
+        }
+    }
+
+    #[test]
+    fn environment_variables_override_the_file() {
+        let file = TempFile::new("[pool]\nmax_size = 4\nwait_timeout_ms = 100\n");
+        let mut config = AllowlistGuardConfig::from_file(&file.0).unwrap();
+        config
+            .apply_vars(vars(&[
+                ("DATABASE_URL", "postgresql://env/allowlist"),
+                ("DB_POOL_MAX_SIZE", "8"),
+                ("DB_FAIL_POLICY", "open"),
+                ("SERVE", "both"),
+                ("SNAPSHOT_DIR", "/var/lib/bloom"),
+                ("VOUCHER_CHAIN_ID", "10"),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.database.url, "postgresql://env/allowlist");
+        assert_eq!((config.pool.max_size, config.pool.wait_timeout_ms), (8, 100));
+        assert_eq!(config.checks.fail_policy, FailPolicy::FailOpen);
+        assert_eq!(config.server.serve, Serve::Both);
+        assert_eq!(config.snapshots.dir, Some(PathBuf::from("/var/lib/bloom")));
+        assert_eq!(config.vouchers.chain_id, Some(10));
+    }
+
+    #[test]
+    fn environment_errors_name_the_variable() {
+        let cases = [
+            ("DB_POOL_MAX_SIZE", "many"),
+            ("FILTER_FALSE_POSITIVE_RATE", "low"),
+            ("CACHE_POSITIVES", "yes please"),
+            ("DB_FAIL_POLICY", "sometimes"),
+            ("GUARD_MODE", "staging"),
+            ("VOUCHER_CHAIN_ID", "-1"),
+        ];
+        for (var, value) in cases {
+            assert_eq!(env_var(valid().apply_vars(vars(&[(var, value)]))), var);
+        }
+
+        let not_unicode = Vars(|var: &str| match var {
+            "RUST_LOG" => Err(VarError::NotUnicode(OsString::from("info"))),
+            _ => Err(VarError::NotPresent),
+        });
+        assert_eq!(env_var(valid().apply_vars(not_unicode)), "RUST_LOG");
+    }
+
+    #[test]
+    fn retry_uses_the_configured_attempts_and_backoff() {
+        let mut config = valid();
+        config
+            .apply_vars(vars(&[("DB_FAIL_POLICY", "retry"), ("DB_RETRY_ATTEMPTS", "3"), ("DB_RETRY_BACKOFF_MS", "75")]))
+            .unwrap();
+        assert_eq!(
+            config.check_policy().on_db_failure,
+            FailPolicy::Retry { attempts: 3, backoff: Duration::from_millis(75) }
+        );
+
+        // The other policies ignore them
+        config.apply_vars(vars(&[("DB_FAIL_POLICY", "closed")])).unwrap();
+        assert_eq!(config.check_policy().on_db_failure, FailPolicy::FailClosed);
+    }
+
+    #[test]
+    fn validate_names_the_bad_setting() {
+        assert!(valid().validate().is_ok());
+        assert_eq!(invalid_field(AllowlistGuardConfig::default().validate()), "database.url");
+
+        let cases: [(&str, Breakage); 12] = [
+            ("pool.max_size", |config| config.pool.max_size = 0),
+            ("pool", |config| config.pool.connect_timeout_ms = 0),
+            ("filter.expected_items", |config| config.filter.expected_items = 0),
+            ("filter.false_positive_rate", |config| config.filter.false_positive_rate = 1.0),
+            ("filter.false_positive_rate", |config| config.filter.false_positive_rate = f64::NAN),
+            ("cache.size", |config| config.cache.size = 0),
+            ("checks.timeout_ms", |config| config.checks.timeout_ms = 0),
+            ("server.listen_addr", |config| config.server.listen_addr = "localhost".to_string()),
+            ("snapshots.interval_secs", |config| {
+                config.snapshots.dir = Some(PathBuf::from("/tmp"));
+                config.snapshots.interval_secs = 0;
+            }),
+            ("sync.resync_interval_secs", |config| config.sync.resync_interval_secs = 0),
+            ("vouchers.max_quantity", |config| config.vouchers.max_quantity = 0),
+            ("vouchers.chain_id", |config| {
+                config.vouchers.signing_key = Some("0x01".to_string());
+            }),
+        ];
+        for (field, break_it) in cases {
+            let mut config = valid();
+            break_it(&mut config);
+            assert_eq!(invalid_field(config.validate()), field);
+        }
+    }
+
+    #[test]
+    fn only_the_served_apis_need_a_valid_address() {
+        let mut config = valid();
+        config.server.grpc_listen_addr = "nowhere".to_string();
+        assert!(config.validate().is_ok());
+
+        config.server.serve = Serve::Grpc;
+        assert_eq!(invalid_field(config.validate()), "server.grpc_listen_addr");
+    }
+}
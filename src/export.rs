@@ -0,0 +1,205 @@
+//! Getting a list back out of Postgres: CSV / JSON lines dumps, and a Merkle root
+//! with per-wallet proofs for publishing the same list on-chain.
+//!
+//! Rows are read in keyset pages, so dumps of any size are streamed without
+//! holding the whole list in memory. Merkle exports need every leaf and are built in full.
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use futures_util::{Stream, stream};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::address::Chain;
+use crate::guard::AllowlistGuard;
+use crate::merkle::{self, MerkleTree};
+use crate::models;
+
+// How `created_at` is written in CSV, matching the JSON form
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Output of an export
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// `wallet,chain,created_at` with a header, re-importable as is
+    #[default]
+    Csv,
+    /// One `{"chain","wallet","created_at"}` object per line
+    Jsonl,
+    /// Merkle root plus a proof for every wallet
+    Merkle,
+}
+
+/// Errors about export parameters
+#[derive(Debug)]
+pub enum ExportError {
+    /// A `created_at` bound that is not a date or timestamp
+    InvalidTimestamp(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::InvalidTimestamp(raw) => write!(
+                f,
+                "'{}' is not a valid timestamp, expected YYYY-MM-DD, YYYY-MM-DDTHH:MM:SS or RFC 3339",
+                raw
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// Parse a `created_at` bound. Dates mean midnight; RFC 3339 input is converted to UTC.
+pub fn parse_timestamp(raw: &str) -> Result<NaiveDateTime, ExportError> {
+    let raw = raw.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap());
+    }
+    if let Ok(timestamp) = NaiveDateTime::parse_from_str(raw, TIMESTAMP_FORMAT) {
+        return Ok(timestamp);
+    }
+    DateTime::parse_from_rfc3339(raw)
+        .map(|timestamp| timestamp.naive_utc())
+        .map_err(|_| ExportError::InvalidTimestamp(raw.to_string()))
+}
+
+#[derive(Serialize)]
+struct JsonRow<'a> {
+    chain: &'a str,
+    wallet: &'a str,
+    created_at: Option<NaiveDateTime>,
+}
+
+/// Stream a list as CSV or JSON lines, one chunk per page of rows.
+/// `from` / `to` bound `created_at` (inclusive / exclusive).
+pub fn export_lines(
+    guard: Arc<AllowlistGuard>,
+    list_pk: i32,
+    format: ExportFormat,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+) -> impl Stream<Item = Result<String>> + Send + 'static {
+    // State: cursor of the next page (`None` once exhausted), and whether this is the first chunk
+    stream::try_unfold((Some(0), true), move |(cursor, first)| {
+        let guard = Arc::clone(&guard);
+        async move {
+            let Some(cursor) = cursor else {
+                return Ok(None);
+            };
+            let page = guard.entries_page(list_pk, cursor, from, to).await?;
+
+            let mut chunk = String::new();
+            if first && format == ExportFormat::Csv {
+                chunk.push_str("wallet,chain,created_at\n");
+            }
+            for entry in &page {
+                write_row(&mut chunk, format, entry)?;
+            }
+
+            // An empty page ends the stream, unless the header still has to go out
+            if page.is_empty() && !first {
+                return Ok(None);
+            }
+            let next = page.last().map(|entry| entry.id);
+            Ok(Some((chunk, (next, false))))
+        }
+    })
+}
+
+fn write_row(out: &mut String, format: ExportFormat, entry: &models::ExportEntry) -> Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            let created = entry
+                .created_at
+                .map(|timestamp| timestamp.format(TIMESTAMP_FORMAT).to_string())
+                .unwrap_or_default();
+            writeln!(out, "{},{},{}", entry.wallet_address, entry.chain, created)?;
+        }
+        ExportFormat::Jsonl => {
+            let row = JsonRow {
+                chain: &entry.chain,
+                wallet: &entry.wallet_address,
+                created_at: entry.created_at,
+            };
+            out.push_str(&serde_json::to_string(&row)?);
+            out.push('\n');
+        }
+        ExportFormat::Merkle => unreachable!("Merkle exports are not line based"),
+    }
+    Ok(())
+}
+
+/// A wallet's leaf and the proof that it is under the root
+#[derive(Debug, Serialize)]
+pub struct WalletProof {
+    pub chain: Chain,
+    pub wallet: String,
+    pub leaf: String,
+    pub proof: Vec<String>,
+}
+
+/// Merkle artifacts of a list
+#[derive(Debug, Serialize)]
+pub struct MerkleExport {
+    pub root: String,
+    pub leaves: usize,
+    /// Stored rows that are not valid addresses and were left out of the tree
+    pub skipped: usize,
+    pub proofs: Vec<WalletProof>,
+}
+
+/// Build the Merkle tree of a list and a proof for each of its wallets
+pub async fn export_merkle(
+    guard: &AllowlistGuard,
+    list_pk: i32,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+) -> Result<MerkleExport> {
+    let mut wallets = Vec::new();
+    let mut skipped = 0;
+    let mut cursor = 0;
+    loop {
+        let page = guard.entries_page(list_pk, cursor, from, to).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        cursor = last.id;
+
+        for entry in &page {
+            match entry.wallet() {
+                Ok(wallet) => wallets.push(wallet),
+                Err(_) => skipped += 1,
+            }
+        }
+    }
+
+    let leaves: Vec<merkle::Hash> = wallets.iter().map(merkle::leaf).collect();
+    let tree = MerkleTree::new(leaves.clone());
+    let proofs = wallets
+        .into_iter()
+        .zip(&leaves)
+        .map(|(wallet, leaf)| WalletProof {
+            chain: wallet.chain(),
+            wallet: wallet.to_string(),
+            leaf: merkle::to_hex(leaf),
+            proof: tree
+                .proof(leaf)
+                .unwrap_or_default()
+                .iter()
+                .map(merkle::to_hex)
+                .collect(),
+        })
+        .collect();
+
+    Ok(MerkleExport {
+        root: merkle::to_hex(&tree.root()),
+        leaves: tree.len(),
+        skipped,
+        proofs,
+    })
+}
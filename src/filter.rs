@@ -0,0 +1,283 @@
+use rand::RngCore;
+use siphasher::sip::SipHasher13;
+use std::f64::consts::LN_2;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+
+// Far above the optimal `k` of any sensible false positive rate
+const MAX_K_NUM: u32 = 64;
+
+/// A counting Bloom filter: every slot is an 8-bit counter instead of a bit,
+/// so items can be removed again as long as they were actually inserted.
+///
+/// Hashing mirrors the `bloomfilter` crate: two SipHash-1-3 instances keyed
+/// from a 32-byte seed, combined with double hashing to derive `k` indexes.
+/// Counters saturate at `u8::MAX` and are never decremented past that point,
+/// which keeps the filter free of false negatives under heavy collisions.
+#[derive(Clone, Debug)]
+pub struct CountingBloom {
+    counters: Vec<u8>,
+    k_num: u32,
+    seed: [u8; 32],
+    sips: [SipHasher13; 2],
+}
+
+impl CountingBloom {
+    /// Create a filter sized for `items_count` items at the wanted false positive rate
+    pub fn new_for_fp_rate(items_count: usize, fp_p: f64) -> Self {
+        let mut seed = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut seed);
+        Self::new_with_seed(Self::compute_counters_len(items_count, fp_p), items_count, &seed)
+    }
+
+    /// Create a filter with `counters_len` slots and explicit hash keys
+    pub fn new_with_seed(counters_len: usize, items_count: usize, seed: &[u8; 32]) -> Self {
+        assert!(counters_len > 0 && items_count > 0);
+        let k_num = Self::optimal_k_num(counters_len as u64, items_count);
+
+        Self {
+            counters: vec![0; counters_len],
+            k_num,
+            seed: *seed,
+            sips: Self::sips_from_seed(seed),
+        }
+    }
+
+    /// Record an item, bumping each of its `k` counters
+    pub fn set<T: Hash + ?Sized>(&mut self, item: &T) {
+        let mut hashes = [0u64; 2];
+        for k_i in 0..self.k_num {
+            let idx = self.index(&mut hashes, k_i, item);
+            self.counters[idx] = self.counters[idx].saturating_add(1);
+        }
+    }
+
+    /// Check if an item is possibly present
+    pub fn check<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        let mut hashes = [0u64; 2];
+        (0..self.k_num).all(|k_i| self.counters[self.index(&mut hashes, k_i, item)] > 0)
+    }
+
+    /// Remove a previously inserted item.
+    /// Returns `false` without touching the counters if the item was not present.
+    pub fn remove<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        if !self.check(item) {
+            return false;
+        }
+
+        let mut hashes = [0u64; 2];
+        for k_i in 0..self.k_num {
+            let idx = self.index(&mut hashes, k_i, item);
+            if self.counters[idx] != u8::MAX {
+                self.counters[idx] -= 1;
+            }
+        }
+        true
+    }
+
+    /// Share of counters that are non-zero
+    pub fn fill_ratio(&self) -> f64 {
+        let filled = self.counters.iter().filter(|&&counter| counter > 0).count();
+        filled as f64 / self.counters.len() as f64
+    }
+
+    /// Chance that an item never inserted passes `check`: all `k` of its counters are non-zero
+    pub fn estimated_fp_rate(&self) -> f64 {
+        self.fill_ratio().powi(self.k_num as i32)
+    }
+
+    /// Serialize the counters, `k` and the hash seed
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.k_num.to_le_bytes())?;
+        w.write_all(&self.seed)?;
+        w.write_all(&(self.counters.len() as u64).to_le_bytes())?;
+        w.write_all(&self.counters)
+    }
+
+    /// Inverse of `write_to`. Input claiming more than `max_counters` counters is
+    /// rejected before anything is allocated for them.
+    pub fn read_from<R: Read>(r: &mut R, max_counters: usize) -> io::Result<Self> {
+        let mut k_num = [0u8; 4];
+        r.read_exact(&mut k_num)?;
+        let mut seed = [0u8; 32];
+        r.read_exact(&mut seed)?;
+        let mut counters_len = [0u8; 8];
+        r.read_exact(&mut counters_len)?;
+
+        let k_num = u32::from_le_bytes(k_num);
+        let counters_len = u64::from_le_bytes(counters_len) as usize;
+        if k_num == 0 || counters_len == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "empty counting bloom filter"));
+        }
+        if k_num > MAX_K_NUM || counters_len > max_counters {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("implausible counting bloom filter: {} counters, k = {}", counters_len, k_num),
+            ));
+        }
+
+        let mut counters = vec![0u8; counters_len];
+        r.read_exact(&mut counters)?;
+
+        Ok(Self {
+            counters,
+            k_num,
+            seed,
+            sips: Self::sips_from_seed(&seed),
+        })
+    }
+
+    /// Compute the number of counters needed for `items_count` items at rate `fp_p`
+    pub fn compute_counters_len(items_count: usize, fp_p: f64) -> usize {
+        assert!(items_count > 0);
+        assert!(fp_p > 0.0 && fp_p < 1.0);
+        let log2_2 = LN_2 * LN_2;
+        ((items_count as f64) * f64::ln(fp_p) / -log2_2).ceil() as usize
+    }
+
+    fn optimal_k_num(counters_len: u64, items_count: usize) -> u32 {
+        let m = counters_len as f64;
+        let n = items_count as f64;
+        let k_num = (m / n * LN_2).round() as u32;
+        k_num.max(1)
+    }
+
+    fn sips_from_seed(seed: &[u8; 32]) -> [SipHasher13; 2] {
+        let mut k1 = [0u8; 16];
+        let mut k2 = [0u8; 16];
+        k1.copy_from_slice(&seed[0..16]);
+        k2.copy_from_slice(&seed[16..32]);
+        [SipHasher13::new_with_key(&k1), SipHasher13::new_with_key(&k2)]
+    }
+
+    fn index<T: Hash + ?Sized>(&self, hashes: &mut [u64; 2], k_i: u32, item: &T) -> usize {
+        let hash = if k_i < 2 {
+            let mut sip = self.sips[k_i as usize];
+            item.hash(&mut sip);
+            let hash = sip.finish();
+            hashes[k_i as usize] = hash;
+            hash
+        } else {
+            hashes[0].wrapping_add(u64::from(k_i).wrapping_mul(hashes[1]))
+                % 0xffff_ffff_ffff_ffc5
+        };
+        (hash % self.counters.len() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Distinct halves: each half keys one of the two SipHash instances
+    const SEED: [u8; 32] = *b"0123456789abcdefghijklmnopqrstuv";
+
+    fn sized_for(items_count: usize, fp_p: f64) -> CountingBloom {
+        CountingBloom::new_with_seed(CountingBloom::compute_counters_len(items_count, fp_p), items_count, &SEED)
+    }
+
+    fn key(i: usize) -> String {
+        format!("evm:0x{:040x}", i)
+    }
+
+    #[test]
+    fn removing_every_inserted_item_empties_the_filter() {
+        let mut filter = sized_for(1_000, 0.01);
+        for i in 0..1_000 {
+            filter.set(key(i).as_str());
+        }
+        assert!((0..1_000).all(|i| filter.check(key(i).as_str())));
+
+        // Removing half never evicts the other half
+        for i in 0..500 {
+            assert!(filter.remove(key(i).as_str()));
+        }
+        assert!((500..1_000).all(|i| filter.check(key(i).as_str())));
+
+        for i in 500..1_000 {
+            assert!(filter.remove(key(i).as_str()));
+        }
+        assert!(filter.counters.iter().all(|&counter| counter == 0));
+        assert_eq!(filter.fill_ratio(), 0.0);
+    }
+
+    #[test]
+    fn removing_an_absent_item_changes_nothing() {
+        let mut filter = sized_for(100, 0.01);
+        filter.set("present");
+        let before = filter.counters.clone();
+
+        assert!(!filter.remove("absent"));
+        assert_eq!(filter.counters, before);
+    }
+
+    #[test]
+    fn saturated_counters_are_never_decremented() {
+        let mut filter = sized_for(100, 0.01);
+        for _ in 0..300 {
+            filter.set("hot");
+        }
+        let mut hashes = [0u64; 2];
+        let slots: Vec<usize> = (0..filter.k_num).map(|k_i| filter.index(&mut hashes, k_i, "hot")).collect();
+        assert!(slots.iter().all(|&idx| filter.counters[idx] == u8::MAX));
+
+        // The counters no longer know how many sets they saw, so the item stays present
+        for _ in 0..300 {
+            filter.remove("hot");
+        }
+        assert!(slots.iter().all(|&idx| filter.counters[idx] == u8::MAX));
+        assert!(filter.check("hot"));
+    }
+
+    #[test]
+    fn write_and_read_round_trip() {
+        let mut filter = sized_for(1_000, 0.01);
+        for i in 0..100 {
+            filter.set(key(i).as_str());
+        }
+
+        let mut bytes = Vec::new();
+        filter.write_to(&mut bytes).unwrap();
+        let read = CountingBloom::read_from(&mut bytes.as_slice(), usize::MAX).unwrap();
+
+        assert_eq!(read.k_num, filter.k_num);
+        assert_eq!(read.seed, filter.seed);
+        assert_eq!(read.counters, filter.counters);
+        // Same seed, same hashes: every answer is the same
+        assert!((0..1_000).all(|i| read.check(key(i).as_str()) == filter.check(key(i).as_str())));
+    }
+
+    #[test]
+    fn read_rejects_truncated_input() {
+        let mut bytes = Vec::new();
+        sized_for(1_000, 0.01).write_to(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert!(CountingBloom::read_from(&mut bytes.as_slice(), usize::MAX).is_err());
+    }
+
+    #[test]
+    fn read_rejects_more_counters_than_allowed() {
+        let filter = sized_for(1_000, 0.01);
+        let mut bytes = Vec::new();
+        filter.write_to(&mut bytes).unwrap();
+
+        let len = filter.counters.len();
+        assert!(CountingBloom::read_from(&mut bytes.as_slice(), len).is_ok());
+        assert!(CountingBloom::read_from(&mut bytes.as_slice(), len - 1).is_err());
+    }
+
+    #[test]
+    fn false_positive_rate_holds_at_the_sized_capacity() {
+        let (items_count, fp_p) = (10_000, 0.01);
+        let mut filter = sized_for(items_count, fp_p);
+        for i in 0..items_count {
+            filter.set(key(i).as_str());
+        }
+
+        let probes = 100_000;
+        let false_positives = (items_count..items_count + probes).filter(|&i| filter.check(key(i).as_str())).count();
+        let measured = false_positives as f64 / probes as f64;
+        assert!(measured < fp_p * 1.5, "measured fp rate {}", measured);
+        assert!((filter.estimated_fp_rate() - fp_p).abs() < fp_p * 0.5, "estimated {}", filter.estimated_fp_rate());
+    }
+}
@@ -0,0 +1,233 @@
+//! gRPC front end: the `allowlist.v1.AllowlistGuard` service from `proto/allowlist.proto`,
+//! served by tonic on top of the same `AllowlistGuard` as the HTTP API.
+
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use futures_util::Stream;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tonic::{Code, Request, Response, Status};
+
+use crate::address::{AddressError, Chain, WalletAddress};
+use crate::guard::{AllowlistGuard, ChangeKind, CheckError, CheckOutcome, ClaimError, ListChange, ListError, Tier};
+use crate::schedule::{ScheduleError, Window};
+
+/// Types generated from `proto/allowlist.proto`
+pub mod pb {
+    tonic::include_proto!("allowlist.v1");
+}
+
+use pb::allowlist_guard_server::{AllowlistGuard as GuardRpc, AllowlistGuardServer};
+
+/// The gRPC service around a shared guard
+pub struct GrpcService {
+    guard: Arc<AllowlistGuard>,
+}
+
+/// Build the tonic service, ready for `Server::add_service`
+pub fn server(guard: Arc<AllowlistGuard>) -> AllowlistGuardServer<GrpcService> {
+    AllowlistGuardServer::new(GrpcService { guard })
+}
+
+#[tonic::async_trait]
+impl GuardRpc for GrpcService {
+    async fn check(&self, request: Request<pb::CheckRequest>) -> Result<Response<pb::CheckResponse>, Status> {
+        let request = request.into_inner();
+        let wallet = parse_wallet(&request.chain, &request.wallet)?;
+        let decision = self.guard.check_access(&request.list, &wallet).await.map_err(to_status)?;
+
+        // Same as the HTTP API's 503
+        if decision.outcome.is_failure() && !decision.allowed {
+            return Err(Status::unavailable(CheckError::Unavailable(decision.outcome).to_string()));
+        }
+        Ok(Response::new(pb::CheckResponse {
+            wallet: wallet.to_string(),
+            allowed: decision.allowed,
+            outcome: pb::Outcome::from(decision.outcome).into(),
+            decided_by: pb::Tier::from(decision.tier).into(),
+            window: Some(decision.window.into()),
+        }))
+    }
+
+    /// Malformed addresses are reported per wallet instead of failing the batch
+    async fn check_batch(
+        &self,
+        request: Request<pb::CheckBatchRequest>,
+    ) -> Result<Response<pb::CheckBatchResponse>, Status> {
+        let request = request.into_inner();
+        let chain = parse_chain(&request.chain).map_err(|e| to_status(e.into()))?;
+        let parsed: Vec<Result<WalletAddress, AddressError>> =
+            request.wallets.iter().map(|wallet| WalletAddress::parse(chain, wallet)).collect();
+        let valid: Vec<WalletAddress> = parsed.iter().filter_map(|wallet| wallet.as_ref().ok()).cloned().collect();
+        let decisions = self.guard.check_many(&request.list, &valid).await.map_err(to_status)?;
+
+        let results = request
+            .wallets
+            .into_iter()
+            .zip(parsed)
+            .map(|(raw, wallet)| match wallet {
+                Ok(wallet) => match decisions.get(&wallet) {
+                    Some(decision) => pb::CheckBatchResult {
+                        wallet: wallet.to_string(),
+                        allowed: decision.allowed,
+                        outcome: pb::Outcome::from(decision.outcome).into(),
+                        decided_by: pb::Tier::from(decision.tier).into(),
+                        window: Some(decision.window.clone().into()),
+                        error: None,
+                    },
+                    None => pb::CheckBatchResult { wallet: wallet.to_string(), ..Default::default() },
+                },
+                Err(e) => pb::CheckBatchResult { wallet: raw, error: Some(e.to_string()), ..Default::default() },
+            })
+            .collect();
+
+        Ok(Response::new(pb::CheckBatchResponse { results }))
+    }
+
+    async fn add(&self, request: Request<pb::AddRequest>) -> Result<Response<pb::AddResponse>, Status> {
+        let request = request.into_inner();
+        let wallet = parse_wallet(&request.chain, &request.wallet)?;
+        self.guard
+            .add_user(&request.list, &wallet, request.max_claims, request.tier.as_deref())
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(pb::AddResponse { wallet: wallet.to_string() }))
+    }
+
+    async fn remove(&self, request: Request<pb::RemoveRequest>) -> Result<Response<pb::RemoveResponse>, Status> {
+        let request = request.into_inner();
+        let wallet = parse_wallet(&request.chain, &request.wallet)?;
+        let removed = self.guard.remove_user(&request.list, &wallet).await.map_err(to_status)?;
+
+        Ok(Response::new(pb::RemoveResponse { removed }))
+    }
+
+    async fn stats(&self, request: Request<pb::StatsRequest>) -> Result<Response<pb::StatsResponse>, Status> {
+        let stats = self
+            .guard
+            .list_stats(&request.into_inner().list)
+            .await
+            .map_err(|e| to_status(e.into()))?;
+
+        Ok(Response::new(pb::StatsResponse {
+            items: stats.items as u64,
+            capacity: stats.capacity as u64,
+            cache_hits: stats.cache.hits,
+            cache_misses: stats.cache.misses,
+            cache_entries: stats.cache.entries as u64,
+        }))
+    }
+
+    type WatchChangesStream = Pin<Box<dyn Stream<Item = Result<pb::Change, Status>> + Send + 'static>>;
+
+    /// Ends with `DATA_LOSS` if the client falls too far behind; it should then
+    /// re-read the list and watch again
+    async fn watch_changes(
+        &self,
+        request: Request<pb::WatchChangesRequest>,
+    ) -> Result<Response<Self::WatchChangesStream>, Status> {
+        let list = request.into_inner().list;
+        if !list.is_empty() {
+            self.guard.list_id(&list).await.map_err(|e| to_status(e.into()))?;
+        }
+
+        let changes = BroadcastStream::new(self.guard.subscribe_changes()).filter_map(move |change| match change {
+            Ok(change) if list.is_empty() || change.list == list => Some(Ok(change.into())),
+            Ok(_) => None,
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                Some(Err(Status::data_loss(format!("watcher fell {} changes behind", skipped))))
+            }
+        });
+
+        Ok(Response::new(Box::pin(changes)))
+    }
+}
+
+/// An empty chain means EVM, as an absent one does over HTTP
+fn parse_chain(chain: &str) -> Result<Chain, AddressError> {
+    if chain.is_empty() {
+        return Ok(Chain::default());
+    }
+    chain.parse()
+}
+
+fn parse_wallet(chain: &str, wallet: &str) -> Result<WalletAddress, Status> {
+    parse_chain(chain)
+        .and_then(|chain| WalletAddress::parse(chain, wallet))
+        .map_err(|e| to_status(e.into()))
+}
+
+/// Maps guard errors onto gRPC codes, as `api::ApiError` does onto HTTP statuses
+fn to_status(err: anyhow::Error) -> Status {
+    let code = if err.is::<AddressError>() || err.is::<ClaimError>() || err.is::<ScheduleError>() {
+        Code::InvalidArgument
+    } else if let Some(check_err) = err.downcast_ref::<CheckError>() {
+        match check_err {
+            CheckError::TooManyWallets(_) => Code::InvalidArgument,
+            CheckError::Unavailable(_) => Code::Unavailable,
+        }
+    } else if let Some(list_err) = err.downcast_ref::<ListError>() {
+        match list_err {
+            ListError::NotFound(_) => Code::NotFound,
+            ListError::InvalidName(_) => Code::InvalidArgument,
+        }
+    } else {
+        match err.downcast_ref::<DieselError>() {
+            Some(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Code::AlreadyExists,
+            _ => Code::Internal,
+        }
+    };
+
+    Status::new(code, err.to_string())
+}
+
+impl From<CheckOutcome> for pb::Outcome {
+    fn from(outcome: CheckOutcome) -> Self {
+        match outcome {
+            CheckOutcome::Allowed => pb::Outcome::Allowed,
+            CheckOutcome::Denied => pb::Outcome::Denied,
+            CheckOutcome::DbUnavailable => pb::Outcome::DbUnavailable,
+            CheckOutcome::Timeout => pb::Outcome::Timeout,
+        }
+    }
+}
+
+impl From<Tier> for pb::Tier {
+    fn from(decided_by: Tier) -> Self {
+        match decided_by {
+            Tier::Schedule => pb::Tier::Schedule,
+            Tier::Filter => pb::Tier::Filter,
+            Tier::Cache => pb::Tier::Cache,
+            Tier::Database => pb::Tier::Database,
+        }
+    }
+}
+
+impl From<Window> for pb::Window {
+    fn from(window: Window) -> Self {
+        match window {
+            Window::Open { phase } => {
+                pb::Window { status: pb::WindowStatus::Open.into(), phase, opens_at: None }
+            }
+            Window::NotYetOpen { opens_at } => pb::Window {
+                status: pb::WindowStatus::NotYetOpen.into(),
+                phase: None,
+                opens_at: Some(opens_at.timestamp()),
+            },
+            Window::Closed => pb::Window { status: pb::WindowStatus::Closed.into(), phase: None, opens_at: None },
+        }
+    }
+}
+
+impl From<ListChange> for pb::Change {
+    fn from(change: ListChange) -> Self {
+        let kind = match change.kind {
+            ChangeKind::Added => pb::ChangeKind::Added,
+            ChangeKind::Removed => pb::ChangeKind::Removed,
+        };
+        pb::Change { list: change.list, kind: kind.into(), chain: change.chain, wallet: change.wallet }
+    }
+}
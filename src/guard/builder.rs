@@ -0,0 +1,273 @@
+//! Construction of an `AllowlistGuard` in one of three modes.
+//!
+//! Production never writes on startup (unless asked to migrate) and refuses to
+//! start on a database whose migrations do not match the build. Dev additionally
+//! adds reproducible fake wallets to the default list. Test behaves like
+//! production but keeps away from snapshot files.
+
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use deadpool::Runtime;
+use diesel_async::pooled_connection::deadpool::{Hook, HookError, Pool};
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, PoolError};
+use diesel_async::{AsyncPgConnection, RunQueryDsl, SimpleAsyncConnection};
+use rand::{RngCore, SeedableRng};
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock, broadcast};
+use anyhow::Result;
+use tracing::{info, warn};
+
+use super::{AllowlistGuard, CHANGE_FEED_CAPACITY, CheckPolicy, DEFAULT_LIST, INSTANCE_SETTING};
+use crate::config::{CacheConfig, FilterConfig, PoolConfig};
+use crate::metrics::Metrics;
+use crate::migrations;
+use crate::schema::{bloom_allowlist, lists, phases};
+use crate::voucher::VoucherSigner;
+
+/// How a guard starts up
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GuardMode {
+    /// No seeding; fails if the schema is missing
+    #[default]
+    Production,
+    /// Adds `seed_count` fake wallets to the default list, drawn from an RNG seeded
+    /// with `rng_seed`; every run draws the same wallets, so restarts add none
+    Dev { seed_count: usize, rng_seed: u64 },
+    /// Like production, but snapshots are never read or written
+    Test,
+}
+
+/// The database is not ready for the guard
+#[derive(Debug)]
+pub enum SchemaError {
+    /// A table, or a column of it, does not exist; holds the table and Postgres' message
+    Missing { table: &'static str, reason: String },
+    /// Migrations of this build that the database has not applied
+    Pending(Vec<String>),
+    /// Migrations the database has applied that this build does not know
+    Unknown(Vec<String>),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Missing { table, reason } => {
+                write!(f, "table '{}' is missing or out of date ({}); run the migrations first", table, reason)
+            }
+            SchemaError::Pending(versions) => write!(
+                f,
+                "{} migration(s) not applied ({}); run `migrate` or start with --migrate",
+                versions.len(),
+                versions.join(", ")
+            ),
+            SchemaError::Unknown(versions) => write!(
+                f,
+                "the database has migrations this build does not know ({}); it was migrated by a newer guard",
+                versions.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Builds an `AllowlistGuard`, see `AllowlistGuard::builder`
+pub struct GuardBuilder {
+    db_url: String,
+    mode: GuardMode,
+    migrate: bool,
+    pool: PoolConfig,
+    filter: FilterConfig,
+    cache: CacheConfig,
+    snapshot_dir: Option<PathBuf>,
+    voucher_signer: Option<VoucherSigner>,
+    check_policy: CheckPolicy,
+}
+
+impl GuardBuilder {
+    pub(super) fn new(db_url: &str) -> Self {
+        Self {
+            db_url: db_url.to_string(),
+            mode: GuardMode::default(),
+            migrate: false,
+            pool: PoolConfig::default(),
+            filter: FilterConfig::default(),
+            cache: CacheConfig::default(),
+            snapshot_dir: None,
+            voucher_signer: None,
+            check_policy: CheckPolicy::default(),
+        }
+    }
+
+    /// `GuardMode::Production` unless set
+    pub fn mode(mut self, mode: GuardMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Apply pending migrations before the schema check; off unless set
+    pub fn migrate(mut self, migrate: bool) -> Self {
+        self.migrate = migrate;
+        self
+    }
+
+    /// Size and timeouts of the connection pool
+    pub fn pool(mut self, pool: PoolConfig) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Sizing of new filters
+    pub fn filter(mut self, filter: FilterConfig) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Size and TTL of every list's membership cache, and whether it keeps positives
+    pub fn cache(mut self, cache: CacheConfig) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Where filter snapshots are kept; `None` (the default) disables them
+    pub fn snapshot_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.snapshot_dir = dir;
+        self
+    }
+
+    /// Signer of mint vouchers; `None` (the default) disables them
+    pub fn voucher_signer(mut self, signer: Option<VoucherSigner>) -> Self {
+        self.voucher_signer = signer;
+        self
+    }
+
+    pub fn check_policy(mut self, policy: CheckPolicy) -> Self {
+        self.check_policy = policy;
+        self
+    }
+
+    /// Initialize: Migrate if asked, Connect, Check the Schema, Hydrate Filter, and Seed in dev mode
+    pub async fn build(self) -> Result<Arc<AllowlistGuard>> {
+        // 1. Bring the schema up to date, before any pool connection exists
+        if self.migrate {
+            let applied = migrations::run_pending(&self.db_url).await?;
+            info!(applied = applied.len(), versions = ?applied, "🧬 Ran pending migrations.");
+        }
+
+        // 2. Setup Connection Pool, tagging its connections so we recognize our own change notifications
+        let instance_id = format!("{:016x}", rand::thread_rng().next_u64());
+        let tag = format!("SET {} = '{}'", INSTANCE_SETTING, instance_id);
+        let config = AsyncDieselConnectionManager::<AsyncPgConnection>::new(&self.db_url);
+        let pool = Pool::builder(config)
+            .max_size(self.pool.max_size)
+            .wait_timeout(Some(Duration::from_millis(self.pool.wait_timeout_ms)))
+            .create_timeout(Some(Duration::from_millis(self.pool.connect_timeout_ms)))
+            .runtime(Runtime::Tokio1)
+            .post_create(Hook::async_fn(move |conn: &mut AsyncPgConnection, _| {
+                let tag = tag.clone();
+                Box::pin(async move {
+                    conn.batch_execute(&tag)
+                        .await
+                        .map_err(|e| HookError::Backend(PoolError::QueryError(e)))
+                })
+            }))
+            .build()?;
+
+        info!(mode = ?self.mode, instance = %instance_id, pool_size = self.pool.max_size, "Connected to Postgres via Diesel.");
+        if let Some(signer) = &self.voucher_signer {
+            info!(signer = %signer.signer(), "🔏 Signing mint vouchers.");
+        }
+        info!(policy = ?self.check_policy.on_db_failure, timeout = ?self.check_policy.db_timeout, "🚧 DB failure policy set.");
+
+        let snapshot_dir = match self.mode {
+            GuardMode::Test => None,
+            _ => self.snapshot_dir,
+        };
+        let guard = Arc::new(AllowlistGuard {
+            pool,
+            lists: RwLock::new(HashMap::new()),
+            snapshot_dir,
+            db_url: self.db_url,
+            instance_id,
+            voucher_signer: self.voucher_signer,
+            metrics: Metrics::new()?,
+            check_policy: self.check_policy,
+            sizing: self.filter,
+            cache_config: self.cache,
+            changes: broadcast::channel(CHANGE_FEED_CAPACITY).0,
+        });
+
+        // 3. Refuse to run against a database migrated for another build
+        guard.verify_schema().await?;
+
+        // 4. Hydrate the Bloom Filter from DB
+        guard.hydrate().await?;
+
+        // 5. Add fake wallets in dev; the same seed gives the same wallets, so restarts add nothing
+        if let GuardMode::Dev { seed_count, rng_seed } = self.mode {
+            if guard.lists.read().await.contains_key(DEFAULT_LIST) {
+                let mut rng = StdRng::seed_from_u64(rng_seed);
+                guard.seed(DEFAULT_LIST, seed_count, &mut rng).await?;
+            } else {
+                warn!(list = %DEFAULT_LIST, "⚠️ Default list is gone, not seeding dev wallets.");
+            }
+        }
+
+        Ok(guard)
+    }
+}
+
+impl AllowlistGuard {
+    /// Compare applied migrations with the embedded ones, then select every column
+    /// the guard knows of, from every table it uses
+    async fn verify_schema(&self) -> Result<()> {
+        let mut conn = self.pool.get().await?;
+
+        let applied = migrations::applied_versions(&mut conn).await?;
+        let embedded = migrations::embedded_versions()?;
+        let pending: Vec<String> = embedded.iter().filter(|version| !applied.contains(version)).cloned().collect();
+        if !pending.is_empty() {
+            return Err(SchemaError::Pending(pending).into());
+        }
+        let unknown: Vec<String> = applied.into_iter().filter(|version| !embedded.contains(version)).collect();
+        if !unknown.is_empty() {
+            return Err(SchemaError::Unknown(unknown).into());
+        }
+
+        lists::table
+            .select(lists::all_columns)
+            .limit(0)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| missing_table("lists", e))?;
+        bloom_allowlist::table
+            .select(bloom_allowlist::all_columns)
+            .limit(0)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| missing_table("bloom_allowlist", e))?;
+        phases::table
+            .select(phases::all_columns)
+            .limit(0)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| missing_table("phases", e))?;
+
+        Ok(())
+    }
+}
+
+/// `SchemaError` for "relation / column ... does not exist", other errors as they are
+fn missing_table(table: &'static str, err: DieselError) -> anyhow::Error {
+    match err {
+        DieselError::DatabaseError(_, info) if info.message().contains("does not exist") => {
+            SchemaError::Missing { table, reason: info.message().to_string() }.into()
+        }
+        other => other.into(),
+    }
+}
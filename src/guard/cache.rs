@@ -0,0 +1,182 @@
+//! Memory of recent Postgres answers, so repeated lookups of the same wallet
+//! (typically a bot retrying a filter false positive) skip the database.
+//!
+//! Entries expire after a TTL as a safety net; writes invalidate them right away.
+//! Every invalidation bumps the cache's epoch, and an answer is only stored if the
+//! epoch has not moved since its lookup began, so a lookup that raced a write cannot
+//! put back the answer the write just invalidated.
+
+use lru::LruCache;
+use serde::Serialize;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// What Postgres said about a wallet: its tier if it is on the list, `None` if not
+pub(super) type Membership = Option<String>;
+
+/// Counters of a list's cache
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+/// A bounded LRU of recent answers, keyed by filter key
+pub(super) struct MembershipCache {
+    entries: Mutex<LruCache<String, (Instant, Membership)>>,
+    ttl: Duration,
+    // Positives are only kept if enabled; negatives always are
+    cache_positives: bool,
+    // Bumped under the `entries` lock by every invalidation
+    epoch: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl MembershipCache {
+    pub(super) fn new(capacity: usize, ttl: Duration, cache_positives: bool) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN))),
+            ttl,
+            cache_positives,
+            epoch: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The cached answer for `key`, if there is a fresh one
+    pub(super) fn get(&self, key: &str) -> Option<Membership> {
+        let mut entries = self.entries.lock().unwrap();
+        let cached = match entries.get(key) {
+            Some((stored_at, membership)) if stored_at.elapsed() < self.ttl => Some(membership.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        };
+        drop(entries);
+
+        let counter = if cached.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached
+    }
+
+    /// The current epoch, to be taken before looking up an answer to `put`
+    pub(super) fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::Relaxed)
+    }
+
+    /// Remember what Postgres answered for `key`, unless the cache was invalidated since
+    /// `seen_epoch`, in which case the answer may predate the write
+    pub(super) fn put(&self, key: &str, membership: Membership, seen_epoch: u64) {
+        if membership.is_some() && !self.cache_positives {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if self.epoch.load(Ordering::Relaxed) == seen_epoch {
+            entries.put(key.to_string(), (Instant::now(), membership));
+        }
+    }
+
+    /// Forget `key`, after the wallet was added or removed
+    pub(super) fn invalidate(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        self.epoch.fetch_add(1, Ordering::Relaxed);
+        entries.pop(key);
+    }
+
+    /// Forget everything, after a rebuild may have picked up missed changes
+    pub(super) fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        self.epoch.fetch_add(1, Ordering::Relaxed);
+        entries.clear();
+    }
+
+    pub(super) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(ttl: Duration, cache_positives: bool) -> MembershipCache {
+        MembershipCache::new(16, ttl, cache_positives)
+    }
+
+    #[test]
+    fn answers_are_kept_until_the_ttl() {
+        let cache = cache(Duration::from_millis(50), true);
+        cache.put("a", Some("gold".to_string()), cache.epoch());
+        cache.put("b", None, cache.epoch());
+        assert_eq!(cache.get("a"), Some(Some("gold".to_string())));
+        assert_eq!(cache.get("b"), Some(None));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), None);
+        // Expired entries are dropped on read
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn positives_are_only_kept_if_enabled() {
+        let cache = cache(Duration::from_secs(60), false);
+        cache.put("a", Some("gold".to_string()), cache.epoch());
+        cache.put("b", None, cache.epoch());
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(None));
+    }
+
+    #[test]
+    fn invalidation_forgets_the_answer() {
+        let cache = cache(Duration::from_secs(60), true);
+        cache.put("a", None, cache.epoch());
+        cache.put("b", None, cache.epoch());
+        cache.invalidate("a");
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(None));
+
+        cache.clear();
+        assert_eq!(cache.get("b"), None);
+    }
+
+    #[test]
+    fn answers_looked_up_before_a_write_are_not_stored() {
+        let cache = cache(Duration::from_secs(60), true);
+        // A lookup begins, the wallet is added and invalidated, then the stale answer arrives
+        let seen = cache.epoch();
+        cache.invalidate("a");
+        cache.put("a", None, seen);
+        assert_eq!(cache.get("a"), None);
+
+        let seen = cache.epoch();
+        cache.clear();
+        cache.put("a", None, seen);
+        assert_eq!(cache.get("a"), None);
+
+        cache.put("a", Some("gold".to_string()), cache.epoch());
+        assert_eq!(cache.get("a"), Some(Some("gold".to_string())));
+    }
+
+    #[test]
+    fn hits_and_misses_are_counted() {
+        let cache = cache(Duration::from_secs(60), true);
+        cache.get("a");
+        cache.put("a", None, cache.epoch());
+        cache.get("a");
+        cache.get("a");
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 1, 1));
+    }
+}
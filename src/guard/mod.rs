@@ -0,0 +1,1518 @@
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use diesel_async::pooled_connection::deadpool::Pool;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, broadcast};
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use rand::RngCore;
+use siphasher::sip::SipHasher13;
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::address::{self, Chain, WalletAddress};
+use crate::config::{AllowlistGuardConfig, CacheConfig, FilterConfig};
+use crate::filter::CountingBloom;
+use crate::logging;
+use crate::merkle::{self, MerkleProof, MerkleTree};
+use crate::metrics::Metrics;
+use crate::models;
+use crate::schedule::{self, Phase, Schedule, Window};
+use crate::schema::bloom_allowlist::dsl::*;
+use crate::schema::{lists, phases};
+use crate::snapshot::FilterSnapshot;
+use crate::voucher::{MintVoucher, VoucherError, VoucherSigner};
+
+mod builder;
+mod cache;
+mod policy;
+mod sync;
+
+pub use builder::{GuardBuilder, GuardMode, SchemaError};
+pub use cache::CacheStats;
+pub use policy::{CheckOutcome, CheckPolicy, FailPolicy, PolicyError};
+pub(crate) use policy::{DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_BACKOFF};
+use sync::INSTANCE_SETTING;
+use cache::{Membership, MembershipCache};
+
+// --- CONFIGURATION ---
+// Grow the filter once it holds this share of the items it was sized for
+const RESIZE_THRESHOLD: f64 = 0.9;
+const GROWTH_FACTOR: usize = 2;
+// Rows fetched per keyset page while hydrating
+const HYDRATE_BATCH_SIZE: i64 = 10_000;
+// Ids below a watermark that are read again when catching up from it. Ids are taken at
+// insert, not commit, so a slow transaction can commit rows under the watermark later.
+const WATERMARK_OVERLAP: i32 = 10_000;
+/// Most wallets `check_many` accepts in one call
+pub const MAX_BATCH_CHECK: usize = 10_000;
+// Changes buffered per `subscribe_changes` receiver before it starts lagging
+const CHANGE_FEED_CAPACITY: usize = 1024;
+// Random wallets inserted per statement by `seed`
+const SEED_BATCH_SIZE: usize = 1_000;
+/// List created by the initial migration
+pub const DEFAULT_LIST: &str = "default";
+
+/// Which layer of the guard produced a verdict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tier {
+    /// Rejected in RAM because the list is not open, before the filter was consulted
+    Schedule,
+    /// Rejected in RAM by the Bloom filter, Postgres was never touched
+    Filter,
+    /// The filter passed and a recent Postgres answer was reused
+    Cache,
+    /// The filter passed and Postgres gave the final answer
+    Database,
+}
+
+impl Tier {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tier::Schedule => "schedule",
+            Tier::Filter => "filter",
+            Tier::Cache => "cache",
+            Tier::Database => "database",
+        }
+    }
+}
+
+/// Result of `check_access`: the verdict, what the check found out, the tier that
+/// decided it, and where the list stands for the wallet right now
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessDecision {
+    /// The verdict, after the fail policy was applied to failed lookups
+    pub allowed: bool,
+    pub outcome: CheckOutcome,
+    #[serde(rename = "decided_by")]
+    pub tier: Tier,
+    /// For the wallet's own tier once Postgres confirmed it, otherwise for any tier
+    pub window: Window,
+}
+
+impl AccessDecision {
+    fn denied(decided_by: Tier, window: Window) -> Self {
+        Self { allowed: false, outcome: CheckOutcome::Denied, tier: decided_by, window }
+    }
+
+    /// A confirmed member, allowed if its tier's window is open
+    fn member(decided_by: Tier, window: Window) -> Self {
+        let allowed = window.is_open();
+        let outcome = if allowed { CheckOutcome::Allowed } else { CheckOutcome::Denied };
+        Self { allowed, outcome, tier: decided_by, window }
+    }
+}
+
+/// Errors about the named list a request targets
+#[derive(Debug)]
+pub enum ListError {
+    /// No list with that name exists
+    NotFound(String),
+    /// The name cannot be used for a list
+    InvalidName(String),
+}
+
+impl fmt::Display for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListError::NotFound(list) => write!(f, "allowlist '{}' does not exist", list),
+            ListError::InvalidName(list) => write!(f, "'{}' is not a valid allowlist name", list),
+        }
+    }
+}
+
+impl std::error::Error for ListError {}
+
+/// Result of `claim`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClaimOutcome {
+    /// The wallet is not on the list
+    NotListed,
+    /// The wallet is on the list, but no phase admits its tier right now
+    OutsideWindow { window: Window },
+    /// The claim was recorded; `remaining` claims are left
+    Claimed { remaining: i32 },
+    /// The wallet has fewer than the requested claims left; nothing was recorded
+    Exceeded { remaining: i32 },
+}
+
+/// Errors about a claim request
+#[derive(Debug)]
+pub enum ClaimError {
+    /// Quantities must be positive
+    InvalidQuantity(i32),
+    /// A wallet's claim limit cannot be negative
+    InvalidMaxClaims(i32),
+}
+
+impl fmt::Display for ClaimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClaimError::InvalidQuantity(quantity) => write!(f, "claim quantity must be positive, got {}", quantity),
+            ClaimError::InvalidMaxClaims(limit) => write!(f, "max_claims cannot be negative, got {}", limit),
+        }
+    }
+}
+
+impl std::error::Error for ClaimError {}
+
+/// Errors about a check request
+#[derive(Debug)]
+pub enum CheckError {
+    /// More than `MAX_BATCH_CHECK` wallets
+    TooManyWallets(usize),
+    /// Postgres could not confirm a wallet that needs confirming whatever the fail policy
+    Unavailable(CheckOutcome),
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckError::TooManyWallets(count) => {
+                write!(f, "at most {} wallets can be checked at once, got {}", MAX_BATCH_CHECK, count)
+            }
+            CheckError::Unavailable(CheckOutcome::Timeout) => write!(f, "the database did not answer in time"),
+            CheckError::Unavailable(_) => write!(f, "the database is unavailable"),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+/// What happened to the wallet in a `ListChange`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+}
+
+/// A wallet added to or removed from a list, on this replica or another
+#[derive(Debug, Clone)]
+pub struct ListChange {
+    pub list: String,
+    pub kind: ChangeKind,
+    pub chain: String,
+    pub wallet: String,
+}
+
+/// Size, health and cache counters of one list
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ListStats {
+    /// Wallets in the filter
+    pub items: usize,
+    /// Wallets the filter is sized for
+    pub capacity: usize,
+    /// Share of filter counters that are non-zero
+    pub fill_ratio: f64,
+    /// Chance that a wallet not on the list passes the filter
+    pub estimated_fp_rate: f64,
+    pub cache: CacheStats,
+}
+
+/// A list's Merkle tree, with the `merkle_version` it was built at and the
+/// `rows_digest` of the rows it was built from
+struct CachedTree {
+    version: u64,
+    digest: u64,
+    tree: Arc<MerkleTree>,
+}
+
+/// In-memory state of one named allowlist
+struct ListState {
+    id: i32,
+    filter: RwLock<CountingBloom>,
+    // Number of items the current filter was sized for
+    capacity: AtomicUsize,
+    // Number of wallets currently inserted into the filter
+    items: AtomicUsize,
+    // Highest `bloom_allowlist.id` the filter covers, persisted with snapshots
+    watermark: AtomicI32,
+    // `Some` while a rebuild is running: filter keys added meanwhile, replayed before the swap
+    resize_journal: Mutex<Option<Vec<String>>>,
+    // Bumped whenever the filter is swapped or caught up from the DB, see `apply_delete`
+    generation: AtomicU64,
+    // Latest `generation` announced through the change listener; deletes notified
+    // before that announcement may predate the swap
+    synced_generation: AtomicU64,
+    // Bumped on every change to the list, so a cached Merkle tree knows it is stale
+    merkle_version: AtomicU64,
+    // Merkle tree over the list, rebuilt when `merkle_version` moves past it
+    merkle: Mutex<Option<CachedTree>>,
+    // Held while the tree is rebuilt, so concurrent proof requests wait for one build
+    merkle_build: tokio::sync::Mutex<()>,
+    // Window and phases, swapped whole when they change
+    schedule: Mutex<Arc<Schedule>>,
+    // Recent Postgres answers for wallets that passed the filter
+    cache: MembershipCache,
+}
+
+impl ListState {
+    fn new(list_pk: i32, sizing: &FilterConfig, cache: &CacheConfig) -> Self {
+        Self {
+            id: list_pk,
+            filter: RwLock::new(CountingBloom::new_for_fp_rate(sizing.expected_items, sizing.false_positive_rate)),
+            capacity: AtomicUsize::new(sizing.expected_items),
+            items: AtomicUsize::new(0),
+            watermark: AtomicI32::new(0),
+            resize_journal: Mutex::new(None),
+            generation: AtomicU64::new(0),
+            synced_generation: AtomicU64::new(0),
+            merkle_version: AtomicU64::new(0),
+            merkle: Mutex::new(None),
+            merkle_build: tokio::sync::Mutex::new(()),
+            schedule: Mutex::new(Arc::new(Schedule::default())),
+            cache: MembershipCache::new(cache.size, Duration::from_secs(cache.ttl_secs), cache.positives),
+        }
+    }
+
+    /// The cached Merkle tree, if it is still current
+    fn current_tree(&self) -> Option<Arc<MerkleTree>> {
+        let version = AtomicU64::load(&self.merkle_version, Ordering::Relaxed);
+        self.merkle
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|cached| cached.version == version)
+            .map(|cached| Arc::clone(&cached.tree))
+    }
+
+    fn schedule(&self) -> Arc<Schedule> {
+        Arc::clone(&self.schedule.lock().unwrap())
+    }
+
+    /// Set a wallet's filter key in a list's filter (and the journal, if a rebuild is in flight)
+    async fn apply_insert(&self, key: &str, row_id: i32) {
+        let mut filter = self.filter.write().await;
+        filter.set(key);
+        if let Some(journal) = self.resize_journal.lock().unwrap().as_mut() {
+            journal.push(key.to_string());
+        }
+        self.watermark.fetch_max(row_id, Ordering::Relaxed);
+        drop(filter);
+        self.cache.invalidate(key);
+        self.items.fetch_add(1, Ordering::Relaxed);
+        self.merkle_version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// `apply_insert` for a row that may already be in the filter. The key is set again
+    /// either way, which at worst leaves a stale positive, but only counted as a new item
+    /// if it is above the watermark or the filter did not have it. Returns whether it was new.
+    async fn reapply_insert(&self, key: &str, row_id: i32) -> bool {
+        let mut filter = self.filter.write().await;
+        let is_new = row_id > AtomicI32::load(&self.watermark, Ordering::Relaxed) || !filter.check(key);
+        filter.set(key);
+        if let Some(journal) = self.resize_journal.lock().unwrap().as_mut() {
+            journal.push(key.to_string());
+        }
+        self.watermark.fetch_max(row_id, Ordering::Relaxed);
+        drop(filter);
+        self.cache.invalidate(key);
+        if is_new {
+            self.items.fetch_add(1, Ordering::Relaxed);
+            self.merkle_version.fetch_add(1, Ordering::Relaxed);
+        }
+        is_new
+    }
+
+    /// Clear a wallet's filter key from a list's filter. `seen_generation` is the filter's
+    /// `generation` from before the row was deleted: a filter swapped in or caught up since
+    /// may never have held the key, and removing it anyway would decrement counters other
+    /// wallets rely on. The key then stays set, a stale positive until the next rebuild.
+    async fn apply_delete(&self, key: &str, seen_generation: u64) {
+        let mut filter = self.filter.write().await;
+        if AtomicU64::load(&self.generation, Ordering::Relaxed) == seen_generation {
+            filter.remove(key);
+        }
+        drop(filter);
+        self.cache.invalidate(key);
+        let _ = self.items.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        self.merkle_version.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The "Guard" holds the state
+pub struct AllowlistGuard {
+    // Diesel Async Pool
+    pool: Pool<AsyncPgConnection>,
+    // One filter per named list
+    lists: RwLock<HashMap<String, Arc<ListState>>>,
+    // Where filter snapshots are kept; `None` disables them
+    snapshot_dir: Option<PathBuf>,
+    // Needed for the dedicated LISTEN connection, which cannot come from the pool
+    db_url: String,
+    // Random id set as `bloom_allowlist.instance` on our pool connections; change
+    // notifications carrying it are our own and already applied
+    instance_id: String,
+    // Signs EIP-712 mint vouchers; `None` disables them
+    voucher_signer: Option<VoucherSigner>,
+    // Prometheus counters, histograms and gauges
+    metrics: Metrics,
+    // Timeout and fail policy of membership lookups
+    check_policy: CheckPolicy,
+    // Size and false positive rate of new filters
+    sizing: FilterConfig,
+    // Size, TTL and positives setting of every list's membership cache
+    cache_config: CacheConfig,
+    // Feed of wallet changes for watchers
+    changes: broadcast::Sender<ListChange>,
+}
+
+impl AllowlistGuard {
+    /// Connect, hydrate and start in the configured mode, as `serve` does
+    pub async fn new(config: &AllowlistGuardConfig) -> Result<Arc<Self>> {
+        Self::builder(&config.database.url)
+            .mode(config.guard_mode())
+            .migrate(config.startup.migrate)
+            .pool(config.pool)
+            .filter(config.filter)
+            .cache(config.cache)
+            .snapshot_dir(config.snapshots.dir.clone())
+            .voucher_signer(config.vouchers.signer()?)
+            .check_policy(config.check_policy())
+            .build()
+            .await
+    }
+
+    /// Start building a guard for the database at `db_url`, with default settings
+    /// for anything not set on the builder
+    pub fn builder(db_url: &str) -> GuardBuilder {
+        GuardBuilder::new(db_url)
+    }
+
+    /// Helper to populate one Bloom Filter per list,
+    /// from its snapshot when there is one, otherwise from the DB
+    #[instrument(skip_all)]
+    async fn hydrate(self: &Arc<Self>) -> Result<()> {
+        let mut conn = self.pool.get().await?;
+        let all_lists = lists::table
+            .select(models::List::as_select())
+            .load(&mut conn)
+            .await?;
+        drop(conn);
+
+        for list in all_lists {
+            let state = Arc::new(ListState::new(list.id, &self.sizing, &self.cache_config));
+            *state.schedule.lock().unwrap() = Arc::new(self.load_schedule(list.id).await?);
+            if self.restore_snapshot(&list.name, &state).await? {
+                self.maybe_resize(&list.name, &state);
+            } else {
+                self.rebuild_filter(&list.name, &state, self.sizing.expected_items).await?;
+            }
+            self.lists.write().await.insert(list.name, state);
+        }
+
+        Ok(())
+    }
+
+    /// Build a fresh filter from the DB without holding the lock, then swap it in.
+    /// The new filter is sized for at least `min_capacity` items, and grown further
+    /// if the list is already past the resize threshold for that size.
+    async fn rebuild_filter(&self, list: &str, state: &ListState, min_capacity: usize) -> Result<()> {
+        // Only one rebuild at a time; opening the journal marks it as running
+        {
+            let mut journal = state.resize_journal.lock().unwrap();
+            if journal.is_some() {
+                return Ok(());
+            }
+            *journal = Some(Vec::new());
+        }
+
+        let result = self.build_and_swap(list, state, min_capacity).await;
+        if result.is_err() {
+            // The old filter kept receiving every add, so it is still complete
+            state.resize_journal.lock().unwrap().take();
+        }
+        result
+    }
+
+    async fn build_and_swap(&self, list: &str, state: &ListState, min_capacity: usize) -> Result<()> {
+        // Size the new filter up front so it can be filled while streaming
+        let mut conn = self.pool.get().await?;
+        let total: i64 = bloom_allowlist
+            .filter(list_id.eq(state.id))
+            .count()
+            .get_result(&mut conn)
+            .await?;
+        drop(conn);
+
+        let mut capacity = min_capacity;
+        while total as f64 >= capacity as f64 * RESIZE_THRESHOLD {
+            capacity *= GROWTH_FACTOR;
+        }
+
+        // Fill the new filter offline, `check_access` keeps reading the old one
+        let mut new_filter = CountingBloom::new_for_fp_rate(capacity, self.sizing.false_positive_rate);
+        let mut digest = 0;
+        let (loaded, max_id) = self
+            .stream_entries(list, state.id, 0, total as usize, |entry| {
+                let key = address::filter_key(&entry.chain, &entry.wallet_address);
+                digest = rows_digest(digest, &key);
+                new_filter.set(key.as_str());
+            })
+            .await?;
+
+        // Swap: replay wallets added since the rebuild started.
+        // A wallet that was also in `results` is counted twice, which only adds a
+        // stale positive. Removals are not replayed for the same reason: a removal
+        // that already made it into `results` must not be applied a second time,
+        // so a wallet revoked mid-rebuild stays positive until Postgres rejects it.
+        let mut filter = state.filter.write().await;
+        let journal = state.resize_journal.lock().unwrap().take().unwrap_or_default();
+        for key in &journal {
+            new_filter.set(key.as_str());
+        }
+        *filter = new_filter;
+        let generation = state.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        state.capacity.store(capacity, Ordering::Relaxed);
+        state.items.store(loaded + journal.len(), Ordering::Relaxed);
+        state.watermark.fetch_max(max_id, Ordering::Relaxed);
+        // A rebuild may pick up changes whose notifications were missed; only then is the
+        // Merkle tree stale (changes that were applied bumped the version already)
+        if state.merkle.lock().unwrap().as_ref().is_none_or(|cached| cached.digest != digest) {
+            state.merkle_version.fetch_add(1, Ordering::Relaxed);
+        }
+        state.cache.clear();
+        drop(filter);
+        self.announce_generation(state, generation).await;
+
+        info!(list = %list, wallets = loaded + journal.len(), capacity, "🌊 Hydrated Bloom Filter.");
+        Ok(())
+    }
+
+    /// Page through a list's rows with `id > after_id` in keyset order, handing each to `visit`.
+    /// A pool connection is only held for one page at a time. `expected` is used for
+    /// progress reporting and may be 0 if unknown. Returns the row count and highest id seen.
+    async fn stream_entries(
+        &self,
+        list: &str,
+        list_pk: i32,
+        after_id: i32,
+        expected: usize,
+        mut visit: impl FnMut(&models::AllowlistEntry),
+    ) -> Result<(usize, i32)> {
+        let mut cursor = after_id;
+        let mut loaded = 0;
+        let mut last_report = 0;
+
+        loop {
+            let mut conn = self.pool.get().await?;
+            let page = bloom_allowlist
+                .filter(list_id.eq(list_pk))
+                .filter(id.gt(cursor))
+                .order(id.asc())
+                .limit(HYDRATE_BATCH_SIZE)
+                .select(models::AllowlistEntry::as_select())
+                .load(&mut conn)
+                .await?;
+            drop(conn);
+
+            let Some(last) = page.last() else {
+                break;
+            };
+            cursor = last.id;
+            loaded += page.len();
+            page.iter().for_each(&mut visit);
+
+            // Report every 10% when the total is known, otherwise every page
+            if let Some(percent) = (loaded * 100).checked_div(expected) {
+                let percent = percent.min(100);
+                if percent >= last_report + 10 {
+                    last_report = percent - percent % 10;
+                    info!(list = %list, loaded, expected, percent, "⏳ Hydrating...");
+                }
+            } else if page.len() as i64 == HYDRATE_BATCH_SIZE {
+                info!(list = %list, loaded, "⏳ Hydrating...");
+            }
+
+            if (page.len() as i64) < HYDRATE_BATCH_SIZE {
+                break;
+            }
+        }
+
+        Ok((loaded, cursor))
+    }
+
+    /// Load a list's filter from its snapshot, then catch up on rows inserted after it,
+    /// re-reading the last `WATERMARK_OVERLAP` ids below its watermark.
+    /// Returns `false` if there is no usable snapshot and a full rebuild is needed.
+    ///
+    /// Rows deleted while the guard was down are still set in the restored filter;
+    /// they only cost a Postgres lookup until the next rebuild.
+    async fn restore_snapshot(&self, list: &str, state: &ListState) -> Result<bool> {
+        let Some(dir) = &self.snapshot_dir else {
+            return Ok(false);
+        };
+
+        let path = FilterSnapshot::path_for(dir, state.id);
+        let snapshot = match tokio::task::spawn_blocking(move || FilterSnapshot::load(&path)).await? {
+            Ok(Some(snapshot)) if snapshot.list_id == state.id => snapshot,
+            Ok(_) => return Ok(false),
+            Err(e) => {
+                warn!(list = %list, error = %e, "⚠️ Ignoring unreadable snapshot.");
+                return Ok(false);
+            }
+        };
+
+        // Rows above the watermark, plus an overlap below it for transactions that were
+        // still open when the snapshot was taken. Setting a wallet the snapshot already
+        // holds again only leaves a stale positive once it is deleted.
+        let mut restored = snapshot.filter;
+        let mut newer = 0;
+        let from = snapshot.watermark.saturating_sub(WATERMARK_OVERLAP).max(0);
+        let (_, max_id) = self
+            .stream_entries(list, state.id, from, 0, |entry| {
+                let key = address::filter_key(&entry.chain, &entry.wallet_address);
+                // Count only wallets the snapshot is missing, so the overlap doesn't inflate `items`
+                if entry.id > snapshot.watermark || !restored.check(key.as_str()) {
+                    newer += 1;
+                }
+                restored.set(key.as_str());
+            })
+            .await?;
+        let max_id = max_id.max(snapshot.watermark);
+
+        *state.filter.write().await = restored;
+        state.capacity.store(snapshot.capacity, Ordering::Relaxed);
+        state.items.store(snapshot.items + newer, Ordering::Relaxed);
+        state.watermark.store(max_id, Ordering::Relaxed);
+
+        info!(list = %list, wallets = snapshot.items, newer, "💾 Restored from snapshot and caught up newer rows.");
+        Ok(true)
+    }
+
+    /// Write every list's filter to the snapshot directory
+    pub async fn save_snapshots(&self) -> Result<()> {
+        match &self.snapshot_dir {
+            Some(dir) => self.save_snapshots_to(dir).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Write every list's filter to `dir`, whether or not this guard restores from it
+    pub async fn save_snapshots_to(&self, dir: &Path) -> Result<()> {
+        let all_lists: Vec<(String, Arc<ListState>)> = self
+            .lists
+            .read()
+            .await
+            .iter()
+            .map(|(name, state)| (name.clone(), Arc::clone(state)))
+            .collect();
+
+        for (list, state) in all_lists {
+            // Copy under the read lock so the watermark matches the counters
+            let snapshot = {
+                let filter = state.filter.read().await;
+                FilterSnapshot {
+                    list_id: state.id,
+                    watermark: AtomicI32::load(&state.watermark, Ordering::Relaxed),
+                    capacity: AtomicUsize::load(&state.capacity, Ordering::Relaxed),
+                    items: AtomicUsize::load(&state.items, Ordering::Relaxed),
+                    filter: filter.clone(),
+                }
+            };
+
+            let path = FilterSnapshot::path_for(dir, state.id);
+            let items = snapshot.items;
+            tokio::task::spawn_blocking(move || snapshot.save(&path)).await??;
+            info!(list = %list, wallets = items, "💾 Saved snapshot.");
+        }
+
+        Ok(())
+    }
+
+    /// Save snapshots every `every` in the background
+    pub fn spawn_snapshot_task(self: &Arc<Self>, every: Duration) {
+        let guard = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            // The first tick fires immediately, right after hydration; skip it
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = guard.save_snapshots().await {
+                    error!(error = %e, "💥 Saving snapshots failed.");
+                }
+            }
+        });
+    }
+
+    /// Kick off a background rebuild once a list's filter is close to its sized capacity
+    fn maybe_resize(self: &Arc<Self>, list: &str, state: &Arc<ListState>) {
+        // Fully qualified: diesel's `RunQueryDsl::load` would shadow the atomic one
+        let items = AtomicUsize::load(&state.items, Ordering::Relaxed);
+        let capacity = AtomicUsize::load(&state.capacity, Ordering::Relaxed);
+        if (items as f64) < capacity as f64 * RESIZE_THRESHOLD {
+            return;
+        }
+        if state.resize_journal.lock().unwrap().is_some() {
+            return;
+        }
+
+        info!(list = %list, items, capacity, "📈 Filter is close to capacity, resizing in the background...");
+        let guard = Arc::clone(self);
+        let list = list.to_string();
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            if let Err(e) = guard.rebuild_filter(&list, &state, capacity * GROWTH_FACTOR).await {
+                error!(list = %list, error = %e, "💥 Filter resize failed.");
+            }
+        });
+    }
+
+    /// Look up the in-memory state of a list by name
+    async fn list_state(&self, list: &str) -> Result<Arc<ListState>, ListError> {
+        self.lists
+            .read()
+            .await
+            .get(list)
+            .cloned()
+            .ok_or_else(|| ListError::NotFound(list.to_string()))
+    }
+
+    /// Size and cache counters of a list
+    pub async fn list_stats(&self, list: &str) -> Result<ListStats, ListError> {
+        let state = self.list_state(list).await?;
+        let (fill_ratio, estimated_fp_rate) = {
+            let filter = state.filter.read().await;
+            (filter.fill_ratio(), filter.estimated_fp_rate())
+        };
+        Ok(ListStats {
+            items: AtomicUsize::load(&state.items, Ordering::Relaxed),
+            capacity: AtomicUsize::load(&state.capacity, Ordering::Relaxed),
+            fill_ratio,
+            estimated_fp_rate,
+            cache: state.cache.stats(),
+        })
+    }
+
+    /// All metrics in the Prometheus text format, with filter and pool gauges refreshed
+    pub async fn render_metrics(&self) -> Result<String> {
+        let all_lists: Vec<(String, Arc<ListState>)> = self
+            .lists
+            .read()
+            .await
+            .iter()
+            .map(|(name, state)| (name.clone(), Arc::clone(state)))
+            .collect();
+
+        // Reset first, so deleted lists disappear
+        let metrics = &self.metrics;
+        metrics.filter_items.reset();
+        metrics.filter_capacity.reset();
+        metrics.filter_fill_ratio.reset();
+        metrics.filter_estimated_fp_rate.reset();
+        for (list, state) in all_lists {
+            let (fill_ratio, fp_rate) = {
+                let filter = state.filter.read().await;
+                (filter.fill_ratio(), filter.estimated_fp_rate())
+            };
+            let labels = [list.as_str()];
+            metrics.filter_items.with_label_values(&labels).set(AtomicUsize::load(&state.items, Ordering::Relaxed) as i64);
+            metrics
+                .filter_capacity
+                .with_label_values(&labels)
+                .set(AtomicUsize::load(&state.capacity, Ordering::Relaxed) as i64);
+            metrics.filter_fill_ratio.with_label_values(&labels).set(fill_ratio);
+            metrics.filter_estimated_fp_rate.with_label_values(&labels).set(fp_rate);
+        }
+
+        let status = self.pool.status();
+        let in_use = status.size.saturating_sub(status.available);
+        metrics.pool_max_size.set(status.max_size as i64);
+        metrics.pool_in_use.set(in_use as i64);
+        metrics.pool_idle.set(status.available as i64);
+        metrics.pool_waiting.set(status.waiting as i64);
+        metrics.pool_utilization.set(in_use as f64 / status.max_size.max(1) as f64);
+
+        metrics.encode()
+    }
+
+    /// Primary key of a list, for callers that page through its rows
+    pub async fn list_id(&self, list: &str) -> Result<i32, ListError> {
+        Ok(self.list_state(list).await?.id)
+    }
+
+    /// One page of a list's rows with `id > after_id` in id order, for export.
+    /// `from` / `to` bound `created_at` (inclusive / exclusive).
+    pub async fn entries_page(
+        &self,
+        list_pk: i32,
+        after_id: i32,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<Vec<models::ExportEntry>> {
+        let mut query = bloom_allowlist
+            .filter(list_id.eq(list_pk))
+            .filter(id.gt(after_id))
+            .order(id.asc())
+            .limit(HYDRATE_BATCH_SIZE)
+            .select(models::ExportEntry::as_select())
+            .into_boxed();
+        if let Some(from) = from {
+            query = query.filter(created_at.ge(from));
+        }
+        if let Some(to) = to {
+            query = query.filter(created_at.lt(to));
+        }
+
+        let mut conn = self.pool.get().await?;
+        Ok(query.load(&mut conn).await?)
+    }
+
+    /// Read a list's window and phases from the DB
+    async fn load_schedule(&self, list_pk: i32) -> Result<Schedule> {
+        let mut conn = self.pool.get().await?;
+        // A list deleted meanwhile (its phases cascade and notify) has no schedule left
+        let window = lists::table
+            .find(list_pk)
+            .select(models::ListWindow::as_select())
+            .first(&mut conn)
+            .await
+            .optional()?
+            .unwrap_or(models::ListWindow { starts_at: None, ends_at: None });
+        let list_phases = phases::table
+            .filter(phases::list_id.eq(list_pk))
+            .order((phases::starts_at.asc(), phases::id.asc()))
+            .select(models::PhaseEntry::as_select())
+            .load(&mut conn)
+            .await?;
+
+        Ok(Schedule {
+            starts_at: window.starts_at,
+            ends_at: window.ends_at,
+            phases: list_phases
+                .into_iter()
+                .map(|phase| Phase {
+                    name: phase.name,
+                    starts_at: phase.starts_at,
+                    ends_at: phase.ends_at,
+                    tiers: phase.tiers,
+                })
+                .collect(),
+        })
+    }
+
+    /// Reload a list's schedule after it changed in the DB
+    async fn reload_schedule(&self, state: &ListState) -> Result<()> {
+        let loaded = self.load_schedule(state.id).await?;
+        *state.schedule.lock().unwrap() = Arc::new(loaded);
+        Ok(())
+    }
+
+    /// A list's current window and phases
+    pub async fn schedule(&self, list: &str) -> Result<Arc<Schedule>> {
+        Ok(self.list_state(list).await?.schedule())
+    }
+
+    /// Replace a list's window and all of its phases
+    pub async fn set_schedule(&self, list: &str, new_schedule: Schedule) -> Result<()> {
+        new_schedule.validate(list)?;
+        let state = self.list_state(list).await?;
+        let mut conn = self.pool.get().await?;
+
+        // 1. Replace window and phases in one transaction
+        let list_pk = state.id;
+        let rows: Vec<models::NewPhase> = new_schedule
+            .phases
+            .iter()
+            .map(|phase| models::NewPhase {
+                list_id: list_pk,
+                name: phase.name.clone(),
+                starts_at: phase.starts_at,
+                ends_at: phase.ends_at,
+                tiers: phase.tiers.clone(),
+            })
+            .collect();
+        let (window_start, window_end) = (new_schedule.starts_at, new_schedule.ends_at);
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                diesel::update(lists::table.find(list_pk))
+                    .set((lists::starts_at.eq(window_start), lists::ends_at.eq(window_end)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(phases::table.filter(phases::list_id.eq(list_pk)))
+                    .execute(conn)
+                    .await?;
+                diesel::insert_into(phases::table).values(&rows).execute(conn).await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+        // 2. Swap the cached schedule
+        info!(list = %list, phases = new_schedule.phases.len(), "🗓️ Updated schedule.");
+        *state.schedule.lock().unwrap() = Arc::new(new_schedule);
+
+        Ok(())
+    }
+
+    /// Add `count` random EVM wallets drawn from `rng` to a list, for load tests and demos.
+    /// Returns how many were inserted; wallets already on the list are skipped.
+    pub async fn seed<R: RngCore + Send>(self: &Arc<Self>, list: &str, count: usize, rng: &mut R) -> Result<usize> {
+        info!(list = %list, count, "🏗️ Seeding random wallets...");
+
+        let mut inserted = 0;
+        let mut remaining = count;
+        while remaining > 0 {
+            let batch: Vec<WalletAddress> = (0..remaining.min(SEED_BATCH_SIZE))
+                .map(|_| {
+                    // Generate random 20-byte wallet
+                    let mut random_bytes = [0u8; 20];
+                    rng.fill_bytes(&mut random_bytes);
+                    WalletAddress::from_bytes(random_bytes)
+                })
+                .collect();
+            remaining -= batch.len();
+            inserted += self.insert_batch(list, &batch, None).await?;
+        }
+
+        info!(list = %list, inserted, "✅ Seeding complete.");
+        Ok(inserted)
+    }
+
+    /// Follow wallet changes to every list. A receiver that falls more than
+    /// `CHANGE_FEED_CAPACITY` changes behind gets `RecvError::Lagged`.
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<ListChange> {
+        self.changes.subscribe()
+    }
+
+    fn publish_change(&self, list: &str, kind: ChangeKind, row_chain: &str, row_wallet: &str) {
+        // Nobody watching is not an error
+        let _ = self.changes.send(ListChange {
+            list: list.to_string(),
+            kind,
+            chain: row_chain.to_string(),
+            wallet: row_wallet.to_string(),
+        });
+    }
+
+    /// Names of all lists currently served
+    pub async fn list_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.lists.read().await.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Create a new, empty list
+    pub async fn create_list(&self, list: &str) -> Result<()> {
+        if list.trim().is_empty() || list.contains('/') {
+            return Err(ListError::InvalidName(list.to_string()).into());
+        }
+
+        let mut conn = self.pool.get().await?;
+
+        // 1. Insert into DB
+        let new_list_id: i32 = diesel::insert_into(lists::table)
+            .values(models::NewList { name: list.to_string() })
+            .returning(lists::id)
+            .get_result(&mut conn)
+            .await?;
+
+        // 2. Start with an empty filter
+        self.lists
+            .write()
+            .await
+            .insert(list.to_string(), Arc::new(ListState::new(new_list_id, &self.sizing, &self.cache_config)));
+        info!(list = %list, "🆕 Created list.");
+
+        Ok(())
+    }
+
+    /// Delete a list and every wallet on it.
+    /// Returns `false` if the list did not exist.
+    pub async fn delete_list(&self, list: &str) -> Result<bool> {
+        let mut conn = self.pool.get().await?;
+
+        // 1. Delete from DB (entries go with it via ON DELETE CASCADE)
+        let deleted = diesel::delete(lists::table.filter(lists::name.eq(list)))
+            .execute(&mut conn)
+            .await?;
+
+        // 2. Drop the filter and its snapshot
+        let state = self.lists.write().await.remove(list);
+        if let (Some(dir), Some(state)) = (&self.snapshot_dir, state) {
+            FilterSnapshot::remove(&FilterSnapshot::path_for(dir, state.id))?;
+        }
+
+        if deleted == 0 {
+            return Ok(false);
+        }
+        info!(list = %list, "🗑️ Deleted list.");
+
+        Ok(true)
+    }
+
+    /// The High-Performance Check Logic
+    #[instrument(skip_all, fields(list = %list, wallet = %logging::wallet(wallet_to_check)))]
+    pub async fn check_access(&self, list: &str, wallet_to_check: &WalletAddress) -> Result<AccessDecision> {
+        let started = Instant::now();
+        let state = self.list_state(list).await?;
+        let now = Utc::now();
+        let list_schedule = state.schedule();
+
+        // Step 1: Check the list's window (RAM); nobody gets in while no phase is running
+        let window = list_schedule.window(now, None);
+        if !window.is_open() {
+            debug!("⏰ [Outside Window] List is not open.");
+            self.metrics.schedule_rejections.with_label_values(&[list]).inc();
+            self.metrics.observe_check(Tier::Schedule, started);
+            return Ok(AccessDecision::denied(Tier::Schedule, window));
+        }
+
+        // Step 2: Check Bloom Filter (RAM)
+        let probably_exists = state.filter.read().await.check(wallet_to_check.filter_key().as_str());
+
+        if !probably_exists {
+            debug!("🛑 [Blocked by Filter] Not on the list.");
+            self.metrics.filter_rejections.with_label_values(&[list]).inc();
+            self.metrics.observe_check(Tier::Filter, started);
+            return Ok(AccessDecision::denied(Tier::Filter, window));
+        }
+
+        // Step 3: Check the membership cache, then Postgres (Disk)
+        debug!("⚠️ [Filter Passed] Checking DB...");
+        let lookup = self
+            .with_check_policy(list, "check_access", || self.membership(&state, wallet_to_check))
+            .await;
+        let (wallet_tier, decided_by) = match lookup {
+            Ok(found) => found,
+            Err(outcome) => {
+                self.metrics.observe_check(Tier::Database, started);
+                return Ok(self.failure_decision(outcome, window));
+            }
+        };
+        self.metrics.observe_check(decided_by, started);
+
+        let Some(wallet_tier) = wallet_tier else {
+            debug!("❌ [False Positive] DB rejected the request.");
+            self.metrics.false_positives.with_label_values(&[list]).inc();
+            return Ok(AccessDecision::denied(decided_by, window));
+        };
+        self.metrics.db_confirmations.with_label_values(&[list]).inc();
+
+        // Step 4: The running phase has to admit the wallet's tier
+        let window = list_schedule.window(now, Some(&wallet_tier));
+        if window.is_open() {
+            debug!(decided_by = decided_by.as_str(), "✅ [DB Confirmed] Access Granted.");
+        } else {
+            debug!(wallet_tier = %wallet_tier, "⏰ [DB Confirmed] On the list, but its tier is not admitted now.");
+        }
+
+        Ok(AccessDecision::member(decided_by, window))
+    }
+
+    /// Run a Postgres lookup within the policy's timeout, retrying if the policy says so.
+    /// Returns why it failed if no attempt succeeded.
+    async fn with_check_policy<T, F, Fut>(&self, list: &str, operation: &str, mut lookup: F) -> Result<T, CheckOutcome>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let (retries, backoff) = match self.check_policy.on_db_failure {
+            FailPolicy::Retry { attempts, backoff } => (attempts, backoff),
+            _ => (0, Duration::ZERO),
+        };
+
+        let mut failure = CheckOutcome::DbUnavailable;
+        for attempt in 0..=retries {
+            if attempt > 0 {
+                tokio::time::sleep(backoff * attempt).await;
+            }
+            match tokio::time::timeout(self.check_policy.db_timeout, lookup()).await {
+                Ok(Ok(found)) => return Ok(found),
+                Ok(Err(e)) => {
+                    warn!(error = %e, attempt, "💥 DB lookup failed.");
+                    failure = CheckOutcome::DbUnavailable;
+                }
+                Err(_) => {
+                    warn!(timeout = ?self.check_policy.db_timeout, attempt, "💥 DB lookup timed out.");
+                    failure = CheckOutcome::Timeout;
+                }
+            }
+            self.metrics.errors.with_label_values(&[list, operation]).inc();
+        }
+
+        Err(failure)
+    }
+
+    /// Verdict for a wallet that passed the filter but could not be confirmed
+    fn failure_decision(&self, outcome: CheckOutcome, window: Window) -> AccessDecision {
+        let allowed = self.check_policy.on_db_failure == FailPolicy::FailOpen;
+        if allowed {
+            warn!(outcome = ?outcome, "🚧 Failing open: allowed on the filter's word.");
+        } else {
+            error!(outcome = ?outcome, "🚧 Failing closed: denied without a DB answer.");
+        }
+        AccessDecision { allowed, outcome, tier: Tier::Database, window }
+    }
+
+    /// `check_access` for many wallets at once: the filter is probed for all of them,
+    /// and the survivors that are not cached are confirmed with a single `= ANY` query.
+    #[instrument(skip_all, fields(list = %list, wallets = wallets.len()))]
+    pub async fn check_many(
+        &self,
+        list: &str,
+        wallets: &[WalletAddress],
+    ) -> Result<HashMap<WalletAddress, AccessDecision>> {
+        if wallets.len() > MAX_BATCH_CHECK {
+            return Err(CheckError::TooManyWallets(wallets.len()).into());
+        }
+        let state = self.list_state(list).await?;
+        let now = Utc::now();
+        let list_schedule = state.schedule();
+        let mut decisions = HashMap::with_capacity(wallets.len());
+
+        // Step 1: Check the list's window (RAM)
+        let window = list_schedule.window(now, None);
+        if !window.is_open() {
+            debug!("⏰ [Outside Window] List is not open.");
+            self.metrics.schedule_rejections.with_label_values(&[list]).inc_by(wallets.len() as u64);
+            for wallet in wallets {
+                decisions.insert(wallet.clone(), AccessDecision::denied(Tier::Schedule, window.clone()));
+            }
+            return Ok(decisions);
+        }
+
+        // Step 2: Check Bloom Filter (RAM), under one read lock for the whole batch
+        let mut survivors = Vec::new();
+        {
+            let filter = state.filter.read().await;
+            for wallet in wallets {
+                if filter.check(wallet.filter_key().as_str()) {
+                    survivors.push(wallet);
+                } else {
+                    decisions.insert(wallet.clone(), AccessDecision::denied(Tier::Filter, window.clone()));
+                }
+            }
+        }
+        let blocked = decisions.len();
+        self.metrics.filter_rejections.with_label_values(&[list]).inc_by(blocked as u64);
+
+        // Step 3: Check the membership cache (RAM)
+        let epoch = state.cache.epoch();
+        let mut found: HashMap<&WalletAddress, (Membership, Tier)> = HashMap::new();
+        let mut misses = Vec::new();
+        for wallet in survivors {
+            match state.cache.get(&wallet.filter_key()) {
+                Some(cached) => {
+                    found.insert(wallet, (cached, Tier::Cache));
+                }
+                None => misses.push(wallet),
+            }
+        }
+
+        // Step 4: Check Postgres (Disk), one query for every miss
+        if !misses.is_empty() {
+            let addresses: Vec<&str> = misses.iter().map(|wallet| wallet.as_str()).collect();
+            let lookup = self
+                .with_check_policy(list, "check_many", || self.lookup_tiers(state.id, &addresses))
+                .await;
+            match lookup {
+                Ok(rows) => {
+                    // The same address string may be on the list for another chain, so match on the filter key
+                    let tiers: HashMap<String, String> = rows
+                        .into_iter()
+                        .map(|(row_chain, row_wallet, row_tier)| (address::filter_key(&row_chain, &row_wallet), row_tier))
+                        .collect();
+                    for wallet in &misses {
+                        let key = wallet.filter_key();
+                        let membership = tiers.get(&key).cloned();
+                        state.cache.put(&key, membership.clone(), epoch);
+                        found.insert(wallet, (membership, Tier::Database));
+                    }
+                }
+                Err(outcome) => {
+                    let decision = self.failure_decision(outcome, window.clone());
+                    for wallet in &misses {
+                        decisions.insert((*wallet).clone(), decision.clone());
+                    }
+                }
+            }
+        }
+
+        // Step 5: Members still need a phase that admits their tier
+        for (wallet, (membership, decided_by)) in found {
+            let counter = if membership.is_some() { &self.metrics.db_confirmations } else { &self.metrics.false_positives };
+            counter.with_label_values(&[list]).inc();
+            let decision = match membership {
+                Some(wallet_tier) => AccessDecision::member(decided_by, list_schedule.window(now, Some(&wallet_tier))),
+                None => AccessDecision::denied(decided_by, window.clone()),
+            };
+            decisions.insert(wallet.clone(), decision);
+        }
+
+        debug!(
+            allowed = decisions.values().filter(|decision| decision.allowed).count(),
+            blocked,
+            looked_up = misses.len(),
+            "📋 Checked batch."
+        );
+        Ok(decisions)
+    }
+
+    /// `(chain, wallet_address, tier)` of the list's rows for any of `addresses`
+    async fn lookup_tiers(&self, list_pk: i32, addresses: &[&str]) -> Result<Vec<(String, String, String)>> {
+        let mut conn = self.pool.get().await?;
+        // Diesel Query: SELECT chain, wallet_address, tier ... WHERE list_id = $1 AND wallet_address = ANY($2)
+        Ok(bloom_allowlist
+            .filter(list_id.eq(list_pk))
+            .filter(wallet_address.eq_any(addresses))
+            .select((chain, wallet_address, tier))
+            .load(&mut conn)
+            .await?)
+    }
+
+    /// Whether a wallet that passed the filter is on the list, its tier, and which
+    /// tier answered. Answered from the cache when possible; Postgres answers are cached.
+    async fn membership(&self, state: &ListState, wallet: &WalletAddress) -> Result<(Membership, Tier)> {
+        let key = wallet.filter_key();
+        // Taken before the lookup, so a write that lands during it keeps its answer out of the cache
+        let epoch = state.cache.epoch();
+        if let Some(cached) = state.cache.get(&key) {
+            debug!("💨 [Cache Hit] Answered without the DB.");
+            return Ok((cached, Tier::Cache));
+        }
+
+        let mut conn = self.pool.get().await?;
+        // Diesel Query: SELECT tier ... WHERE list_id = $1 AND chain = $2 AND wallet_address = $3
+        let found: Membership = bloom_allowlist
+            .filter(list_id.eq(state.id))
+            .filter(chain.eq(wallet.chain().as_str()))
+            .filter(wallet_address.eq(wallet.as_str()))
+            .select(tier)
+            .first(&mut conn)
+            .await
+            .optional()?;
+
+        state.cache.put(&key, found.clone(), epoch);
+        Ok((found, Tier::Database))
+    }
+
+    /// Merkle root and proof for a wallet, once `check_access` has confirmed it is on the list.
+    /// Returns `None` if it is not.
+    pub async fn merkle_proof(&self, list: &str, wallet: &WalletAddress) -> Result<Option<MerkleProof>> {
+        if !self.confirmed_access(list, wallet).await? {
+            return Ok(None);
+        }
+
+        let state = self.list_state(list).await?;
+        let tree = self.merkle_tree(list, &state).await?;
+        let leaf = merkle::leaf(wallet);
+
+        Ok(tree.proof(&leaf).map(|proof| MerkleProof { root: tree.root(), leaf, proof }))
+    }
+
+    /// `check_access` for proofs and vouchers: these outlive the request, so they
+    /// need a real answer from Postgres and never fail open
+    async fn confirmed_access(&self, list: &str, wallet: &WalletAddress) -> Result<bool> {
+        let decision = self.check_access(list, wallet).await?;
+        if decision.outcome.is_failure() {
+            return Err(CheckError::Unavailable(decision.outcome).into());
+        }
+        Ok(decision.allowed)
+    }
+
+    /// The list's Merkle tree, rebuilt from the DB if the list changed since it was last built.
+    /// Only one request rebuilds it; the others wait and share the result.
+    async fn merkle_tree(&self, list: &str, state: &ListState) -> Result<Arc<MerkleTree>> {
+        if let Some(tree) = state.current_tree() {
+            return Ok(tree);
+        }
+        let _building = state.merkle_build.lock().await;
+        if let Some(tree) = state.current_tree() {
+            return Ok(tree);
+        }
+
+        // Changes that land while we read are caught by the version check next time
+        let version = AtomicU64::load(&state.merkle_version, Ordering::Relaxed);
+        let mut leaves = Vec::new();
+        let mut digest = 0;
+        let mut cursor = 0;
+        loop {
+            let page = self.entries_page(state.id, cursor, None, None).await?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            cursor = last.id;
+            for entry in &page {
+                digest = rows_digest(digest, &address::filter_key(&entry.chain, &entry.wallet_address));
+            }
+            leaves.extend(page.iter().filter_map(|entry| entry.wallet().ok()).map(|wallet| merkle::leaf(&wallet)));
+        }
+
+        let tree = Arc::new(MerkleTree::new(leaves));
+        info!(list = %list, leaves = tree.len(), "🌳 Built Merkle tree.");
+        *state.merkle.lock().unwrap() = Some(CachedTree { version, digest, tree: Arc::clone(&tree) });
+        Ok(tree)
+    }
+
+    /// Signed EIP-712 voucher for a wallet, once `check_access` has confirmed it is on the list.
+    /// Returns `None` if it is not.
+    ///
+    /// The voucher's quantity is taken from the wallet's claims before it is signed, with
+    /// the same conditional UPDATE as `claim`, so no more vouchers are issued than claims.
+    pub async fn issue_voucher(
+        &self,
+        list: &str,
+        wallet: &WalletAddress,
+        requested: Option<u64>,
+    ) -> Result<Option<MintVoucher>> {
+        let signer = self.voucher_signer.as_ref().ok_or(VoucherError::Disabled)?;
+        if wallet.chain() != Chain::Evm {
+            return Err(VoucherError::UnsupportedChain(wallet.chain()).into());
+        }
+        if !self.confirmed_access(list, wallet).await? {
+            return Ok(None);
+        }
+
+        // 1. The quantity is capped by the config and by the claims the wallet has left
+        let state = self.list_state(list).await?;
+        let mut conn = self.pool.get().await?;
+        let entry = bloom_allowlist
+            .filter(list_id.eq(state.id))
+            .filter(chain.eq(wallet.chain().as_str()))
+            .filter(wallet_address.eq(wallet.as_str()));
+        let Some(remaining) = entry.select(max_claims - claimed).first::<i32>(&mut conn).await.optional()? else {
+            return Ok(None);
+        };
+
+        let allowed = signer.max_quantity().min(remaining.max(0) as u64);
+        let max_quantity = requested.unwrap_or(allowed);
+        if max_quantity == 0 || max_quantity > allowed {
+            return Err(VoucherError::QuantityNotAllowed { requested: max_quantity, allowed }.into());
+        }
+
+        // 2. Reserve it: UPDATE ... SET claimed = claimed + $qty WHERE claimed + $qty <= max_claims
+        let quantity = max_quantity as i32;
+        let reserved: Option<i32> = diesel::update(entry.filter((claimed + quantity).le(max_claims)))
+            .set(claimed.eq(claimed + quantity))
+            .returning(max_claims - claimed)
+            .get_result(&mut conn)
+            .await
+            .optional()?;
+        let Some(remaining) = reserved else {
+            // Another voucher or claim took some of the claims since step 1
+            let left = entry.select(max_claims - claimed).first::<i32>(&mut conn).await.optional()?;
+            let allowed = signer.max_quantity().min(left.unwrap_or(0).max(0) as u64);
+            return Err(VoucherError::QuantityNotAllowed { requested: max_quantity, allowed }.into());
+        };
+        drop(conn);
+
+        // 3. Sign
+        let voucher = signer.sign(wallet, state.id, max_quantity)?;
+        info!(list = %list, wallet = %logging::wallet(wallet), max_quantity, remaining, "🎟️ Issued voucher.");
+
+        Ok(Some(voucher))
+    }
+
+    /// Record `quantity` claims for a wallet, if it is on the list and has that many left.
+    /// The check and the increment are a single conditional UPDATE, so concurrent
+    /// claims can never push a wallet past its `max_claims`.
+    #[instrument(skip_all, fields(list = %list, wallet = %logging::wallet(wallet), quantity))]
+    pub async fn claim(&self, list: &str, wallet: &WalletAddress, quantity: i32) -> Result<ClaimOutcome> {
+        if quantity <= 0 {
+            return Err(ClaimError::InvalidQuantity(quantity).into());
+        }
+        let state = self.list_state(list).await?;
+        let now = Utc::now();
+        let list_schedule = state.schedule();
+
+        // Step 1: Check the list's window (RAM)
+        let window = list_schedule.window(now, None);
+        if !window.is_open() {
+            debug!("⏰ [Outside Window] List is not open.");
+            return Ok(ClaimOutcome::OutsideWindow { window });
+        }
+
+        // Step 2: Check Bloom Filter (RAM)
+        if !state.filter.read().await.check(wallet.filter_key().as_str()) {
+            debug!("🛑 [Blocked by Filter] Not on the list.");
+            return Ok(ClaimOutcome::NotListed);
+        }
+
+        // Step 3: The wallet has to be on the list, in a tier the running phase admits
+        let (Some(wallet_tier), _) = self.membership(&state, wallet).await? else {
+            debug!("❌ [False Positive] Not on the list.");
+            return Ok(ClaimOutcome::NotListed);
+        };
+        let window = list_schedule.window(now, Some(&wallet_tier));
+        if !window.is_open() {
+            debug!(wallet_tier = %wallet_tier, "⏰ On the list, but its tier cannot claim now.");
+            return Ok(ClaimOutcome::OutsideWindow { window });
+        }
+
+        // Step 4: UPDATE ... SET claimed = claimed + $qty WHERE ... AND claimed + $qty <= max_claims
+        let mut conn = self.pool.get().await?;
+        let entry = bloom_allowlist
+            .filter(list_id.eq(state.id))
+            .filter(chain.eq(wallet.chain().as_str()))
+            .filter(wallet_address.eq(wallet.as_str()));
+        let claimed_now: Option<i32> = diesel::update(entry.filter((claimed + quantity).le(max_claims)))
+            .set(claimed.eq(claimed + quantity))
+            .returning(max_claims - claimed)
+            .get_result(&mut conn)
+            .await
+            .optional()?;
+
+        if let Some(remaining) = claimed_now {
+            info!(remaining, "🎁 Claimed.");
+            return Ok(ClaimOutcome::Claimed { remaining });
+        }
+
+        // Step 5: Nothing updated, so the wallet is out of claims (or was removed meanwhile)
+        let remaining: Option<i32> = entry
+            .select(max_claims - claimed)
+            .first(&mut conn)
+            .await
+            .optional()?;
+
+        Ok(match remaining {
+            Some(remaining) => {
+                info!(remaining, "🚫 Not enough claims left.");
+                ClaimOutcome::Exceeded { remaining }
+            }
+            None => {
+                // Removed since the membership lookup, possibly on another replica
+                state.cache.invalidate(&wallet.filter_key());
+                debug!("❌ Removed from the list before the claim.");
+                ClaimOutcome::NotListed
+            }
+        })
+    }
+
+    /// Add a single user to a list, allowed `new_max_claims` claims and placed in
+    /// `new_tier` (the column defaults if `None`)
+    #[instrument(skip_all, fields(list = %list, wallet = %logging::wallet(new_wallet)))]
+    pub async fn add_user(
+        self: &Arc<Self>,
+        list: &str,
+        new_wallet: &WalletAddress,
+        new_max_claims: Option<i32>,
+        new_tier: Option<&str>,
+    ) -> Result<()> {
+        if let Some(limit) = new_max_claims.filter(|limit| *limit < 0) {
+            return Err(ClaimError::InvalidMaxClaims(limit).into());
+        }
+        new_tier.map(schedule::validate_tier).transpose()?;
+        let state = self.list_state(list).await?;
+        let mut conn = self.pool.get().await?;
+
+        // 1. Insert into DB
+        let new_id: i32 = diesel::insert_into(bloom_allowlist)
+            .values(models::NewEntry {
+                wallet_address: new_wallet.to_string(),
+                list_id: state.id,
+                chain: new_wallet.chain().to_string(),
+                max_claims: new_max_claims,
+                tier: new_tier.map(str::to_string),
+            })
+            .returning(id)
+            .get_result(&mut conn)
+            .await?;
+
+        // 2. Update Filter
+        state.apply_insert(&new_wallet.filter_key(), new_id).await;
+        self.publish_change(list, ChangeKind::Added, new_wallet.chain().as_str(), new_wallet.as_str());
+        info!("➕ Added to DB and Bloom Filter.");
+
+        // 3. Grow the filter if we are getting close to its capacity
+        self.maybe_resize(list, &state);
+
+        Ok(())
+    }
+
+    /// Add many wallets to a list in one statement, skipping any already on it.
+    /// New wallets go into `new_tier` (the default tier if `None`).
+    /// Returns how many were actually inserted.
+    pub async fn insert_batch(
+        self: &Arc<Self>,
+        list: &str,
+        wallets: &[WalletAddress],
+        new_tier: Option<&str>,
+    ) -> Result<usize> {
+        new_tier.map(schedule::validate_tier).transpose()?;
+        let state = self.list_state(list).await?;
+        if wallets.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.pool.get().await?;
+
+        // 1. Insert into DB; duplicates (in the DB or within the batch) are skipped
+        let new_entries: Vec<models::NewEntry> = wallets
+            .iter()
+            .map(|wallet| models::NewEntry {
+                wallet_address: wallet.to_string(),
+                list_id: state.id,
+                chain: wallet.chain().to_string(),
+                max_claims: None,
+                tier: new_tier.map(str::to_string),
+            })
+            .collect();
+        let inserted: Vec<(i32, String, String)> = diesel::insert_into(bloom_allowlist)
+            .values(&new_entries)
+            .on_conflict_do_nothing()
+            .returning((id, chain, wallet_address))
+            .get_results(&mut conn)
+            .await?;
+        drop(conn);
+
+        // 2. Update Filter with the rows that made it in
+        for (row_id, row_chain, row_wallet) in &inserted {
+            state.apply_insert(&address::filter_key(row_chain, row_wallet), *row_id).await;
+            self.publish_change(list, ChangeKind::Added, row_chain, row_wallet);
+        }
+
+        // 3. Grow the filter if we are getting close to its capacity
+        self.maybe_resize(list, &state);
+
+        Ok(inserted.len())
+    }
+
+    /// Revoke a single user from a list.
+    /// Returns `false` if the wallet was not on the list.
+    #[instrument(skip_all, fields(list = %list, wallet = %logging::wallet(old_wallet)))]
+    pub async fn remove_user(&self, list: &str, old_wallet: &WalletAddress) -> Result<bool> {
+        let state = self.list_state(list).await?;
+        let generation = AtomicU64::load(&state.generation, Ordering::Relaxed);
+        let mut conn = self.pool.get().await?;
+
+        // 1. Delete from DB
+        let deleted = diesel::delete(
+            bloom_allowlist
+                .filter(list_id.eq(state.id))
+                .filter(chain.eq(old_wallet.chain().as_str()))
+                .filter(wallet_address.eq(old_wallet.as_str())),
+        )
+            .execute(&mut conn)
+            .await?;
+
+        if deleted == 0 {
+            info!("🤷 Not on the list, nothing to remove.");
+            return Ok(false);
+        }
+
+        // 2. Update Filter (only after the row is gone, so the DB stays the source of truth)
+        state.apply_delete(&old_wallet.filter_key(), generation).await;
+        self.publish_change(list, ChangeKind::Removed, old_wallet.chain().as_str(), old_wallet.as_str());
+        info!("➖ Removed from DB and Bloom Filter.");
+
+        Ok(true)
+    }
+}
+
+/// Fold a row's filter key into an order-independent digest of a list's rows, so a
+/// rebuild can tell whether it read the same rows the Merkle tree was built from
+fn rows_digest(digest: u64, key: &str) -> u64 {
+    let mut hasher = SipHasher13::new();
+    key.hash(&mut hasher);
+    digest.wrapping_add(hasher.finish())
+}
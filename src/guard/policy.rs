@@ -0,0 +1,103 @@
+//! What a check concludes, and what to do when Postgres cannot answer.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+// Defaults for `CheckPolicy`
+const DEFAULT_DB_TIMEOUT: Duration = Duration::from_secs(1);
+pub(crate) const DEFAULT_RETRY_ATTEMPTS: u32 = 2;
+pub(crate) const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// What a check found out, before the fail policy turns it into a verdict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckOutcome {
+    /// On the list, and admitted right now
+    Allowed,
+    /// Not on the list, or not admitted right now
+    Denied,
+    /// Postgres (or the pool) returned an error
+    DbUnavailable,
+    /// Postgres did not answer within the policy's timeout
+    Timeout,
+}
+
+impl CheckOutcome {
+    /// Whether the answer came from a failure rather than from the data
+    pub fn is_failure(self) -> bool {
+        matches!(self, CheckOutcome::DbUnavailable | CheckOutcome::Timeout)
+    }
+}
+
+/// What to answer for a wallet that passed the filter when Postgres cannot confirm it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum FailPolicy {
+    /// Deny it
+    FailClosed,
+    /// Allow it: the filter has no false negatives, so only false positives slip through
+    FailOpen,
+    /// Try again up to `attempts` more times, waiting `backoff` longer each time, then deny
+    Retry { attempts: u32, backoff: Duration },
+}
+
+impl FailPolicy {
+    /// `closed`, `open` or `retry` (with the default attempts and backoff)
+    pub fn parse(raw: &str) -> Result<Self, PolicyError> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "closed" | "fail-closed" => Ok(FailPolicy::FailClosed),
+            "open" | "fail-open" => Ok(FailPolicy::FailOpen),
+            "retry" => Ok(FailPolicy::Retry { attempts: DEFAULT_RETRY_ATTEMPTS, backoff: DEFAULT_RETRY_BACKOFF }),
+            other => Err(PolicyError::UnknownPolicy(other.to_string())),
+        }
+    }
+}
+
+impl std::str::FromStr for FailPolicy {
+    type Err = PolicyError;
+
+    fn from_str(raw: &str) -> Result<Self, PolicyError> {
+        Self::parse(raw)
+    }
+}
+
+impl TryFrom<String> for FailPolicy {
+    type Error = PolicyError;
+
+    fn try_from(raw: String) -> Result<Self, PolicyError> {
+        Self::parse(&raw)
+    }
+}
+
+/// How `check_access` treats Postgres
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckPolicy {
+    pub on_db_failure: FailPolicy,
+    /// Longest a single lookup (pool checkout included) may take
+    pub db_timeout: Duration,
+}
+
+impl Default for CheckPolicy {
+    fn default() -> Self {
+        Self { on_db_failure: FailPolicy::FailClosed, db_timeout: DEFAULT_DB_TIMEOUT }
+    }
+}
+
+/// Errors about a configured policy
+#[derive(Debug)]
+pub enum PolicyError {
+    UnknownPolicy(String),
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::UnknownPolicy(raw) => {
+                write!(f, "'{}' is not a fail policy, expected closed, open or retry", raw)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
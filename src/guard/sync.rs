@@ -0,0 +1,314 @@
+//! Cross-replica filter sync over Postgres LISTEN/NOTIFY.
+//!
+//! Triggers on `bloom_allowlist` and `lists` publish every insert and delete on
+//! `CHANGES_CHANNEL`, and triggers on `phases` and list windows publish schedule
+//! changes. Each guard keeps one dedicated connection listening there and applies
+//! changes made by other replicas to its own filters. Whenever the listener
+//! (re)connects, every filter catches up on rows above its watermark; a periodic
+//! full re-hydrate also clears wallets deleted while nobody was listening.
+
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use anyhow::Result;
+use tracing::{debug, error, info, instrument, warn};
+
+use super::{AllowlistGuard, ChangeKind, ListState, WATERMARK_OVERLAP};
+use crate::address::{self, Chain};
+use crate::logging;
+use crate::models;
+use crate::schema::lists;
+use crate::snapshot::FilterSnapshot;
+
+/// Channel the triggers from the `notify_allowlist_changes` migration publish on
+const CHANGES_CHANNEL: &str = "bloom_allowlist_changes";
+/// Session setting the triggers copy into a notification's `origin`
+pub(super) const INSTANCE_SETTING: &str = "bloom_allowlist.instance";
+// How long to wait before reconnecting a dropped listener
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+// Ping the listening connection when it has been quiet this long
+const LISTEN_KEEPALIVE: Duration = Duration::from_secs(30);
+
+/// Payload of a change notification: the change, and the guard instance that made it
+/// (`None` for changes made outside any guard)
+#[derive(Debug, Deserialize)]
+struct Notification {
+    #[serde(default)]
+    origin: Option<String>,
+    #[serde(flatten)]
+    event: ChangeEvent,
+}
+
+/// A change to a list
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum ChangeEvent {
+    Insert {
+        id: i32,
+        list_id: i32,
+        // Absent in payloads from triggers that predate multi-chain support
+        #[serde(default = "default_chain")]
+        chain: String,
+        wallet: String,
+    },
+    Delete {
+        list_id: i32,
+        #[serde(default = "default_chain")]
+        chain: String,
+        wallet: String,
+    },
+    ListInsert { id: i32, name: String },
+    ListDelete { id: i32, name: String },
+    /// A list's window or phases changed
+    Schedule { list_id: i32 },
+    /// A guard swapped in or caught up a list's filter; sent by the guard itself, only
+    /// its own are acted on
+    Rebuilt { list_id: i32, generation: u64 },
+}
+
+fn default_chain() -> String {
+    Chain::Evm.to_string()
+}
+
+impl AllowlistGuard {
+    /// Start the change listener and the periodic full re-hydrate
+    pub fn spawn_sync_tasks(self: &Arc<Self>, resync_every: Duration) {
+        let guard = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = guard.listen().await {
+                    error!(error = %e, "💥 Change listener failed. Reconnecting in {:?}...", RECONNECT_DELAY);
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+
+        let guard = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(resync_every);
+            // The first tick fires immediately, right after hydration; skip it
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = guard.resync().await {
+                    error!(error = %e, "💥 Periodic resync failed.");
+                }
+            }
+        });
+    }
+
+    /// LISTEN on a dedicated connection and apply changes until it fails
+    async fn listen(self: &Arc<Self>) -> Result<()> {
+        let mut conn = AsyncPgConnection::establish(&self.db_url).await?;
+        diesel::sql_query(format!("LISTEN {}", CHANGES_CHANNEL))
+            .execute(&mut conn)
+            .await?;
+        info!(channel = CHANGES_CHANNEL, "📡 Listening for allowlist changes.");
+
+        // Anything that happened before LISTEN is only in the DB now: on a reconnect, changes
+        // made while we were disconnected; on the first connect, those made by other replicas
+        // while the filters were hydrating
+        self.catch_up().await?;
+
+        loop {
+            // The stream replays everything the connection received, so re-creating it loses nothing
+            let next = {
+                let mut notifications = std::pin::pin!(conn.notifications_stream());
+                tokio::time::timeout(LISTEN_KEEPALIVE, notifications.next()).await
+            };
+
+            let notification = match next {
+                Ok(Some(notification)) => notification?,
+                Ok(None) => anyhow::bail!("notification stream closed"),
+                Err(_) => {
+                    // Quiet for a while: make sure the connection is still alive
+                    diesel::sql_query("SELECT 1").execute(&mut conn).await?;
+                    continue;
+                }
+            };
+
+            match serde_json::from_str::<Notification>(&notification.payload) {
+                // Our own writes are already applied locally; our rebuild announcements
+                // mark where deletes become safe to apply again
+                Ok(Notification { origin: Some(origin), event }) if origin == self.instance_id => {
+                    if let ChangeEvent::Rebuilt { list_id, generation } = event
+                        && let Some((_, state)) = self.list_by_id(list_id).await
+                    {
+                        state.synced_generation.fetch_max(generation, Ordering::Relaxed);
+                    }
+                }
+                Ok(Notification { event: ChangeEvent::Rebuilt { .. }, .. }) => {}
+                Ok(Notification { event, .. }) => self.apply_change(event).await?,
+                Err(e) => warn!(error = %e, "⚠️ Ignoring malformed change notification."),
+            }
+        }
+    }
+
+    /// Apply a change made by another replica to the local filters
+    async fn apply_change(self: &Arc<Self>, event: ChangeEvent) -> Result<()> {
+        match event {
+            ChangeEvent::Insert { id, list_id, chain, wallet } => {
+                if let Some((list, state)) = self.list_by_id(list_id).await {
+                    state.apply_insert(&address::filter_key(&chain, &wallet), id).await;
+                    self.publish_change(&list, ChangeKind::Added, &chain, &wallet);
+                    debug!(list = %list, wallet = %logging::address(&chain, &wallet), "🔄 [Sync] Added.");
+                    self.maybe_resize(&list, &state);
+                }
+            }
+            ChangeEvent::Delete { list_id, chain, wallet } => {
+                if let Some((list, state)) = self.list_by_id(list_id).await {
+                    // Deleted before our last rebuild was announced: the filter may not have it
+                    let synced = AtomicU64::load(&state.synced_generation, Ordering::Relaxed);
+                    state.apply_delete(&address::filter_key(&chain, &wallet), synced).await;
+                    self.publish_change(&list, ChangeKind::Removed, &chain, &wallet);
+                    debug!(list = %list, wallet = %logging::address(&chain, &wallet), "🔄 [Sync] Removed.");
+                }
+            }
+            ChangeEvent::ListInsert { id, name } => {
+                if self.lists.read().await.get(&name).is_some_and(|state| state.id == id) {
+                    return Ok(());
+                }
+                let state = Arc::new(ListState::new(id, &self.sizing, &self.cache_config));
+                self.reload_schedule(&state).await?;
+                self.lists.write().await.insert(name.clone(), Arc::clone(&state));
+                // Rows may have been added between the list insert and now
+                self.rebuild_filter(&name, &state, self.sizing.expected_items).await?;
+                info!(list = %name, "🔄 [Sync] Created list.");
+            }
+            ChangeEvent::ListDelete { id, name } => {
+                let mut all_lists = self.lists.write().await;
+                if all_lists.get(&name).is_some_and(|state| state.id == id) {
+                    all_lists.remove(&name);
+                    drop(all_lists);
+                    if let Some(dir) = &self.snapshot_dir {
+                        FilterSnapshot::remove(&FilterSnapshot::path_for(dir, id))?;
+                    }
+                    info!(list = %name, "🔄 [Sync] Deleted list.");
+                }
+            }
+            ChangeEvent::Schedule { list_id } => {
+                if let Some((list, state)) = self.list_by_id(list_id).await {
+                    self.reload_schedule(&state).await?;
+                    info!(list = %list, "🔄 [Sync] Reloaded schedule.");
+                }
+            }
+            ChangeEvent::Rebuilt { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Find a list's name and state by its primary key
+    async fn list_by_id(&self, list_pk: i32) -> Option<(String, Arc<ListState>)> {
+        self.lists
+            .read()
+            .await
+            .iter()
+            .find(|(_, state)| state.id == list_pk)
+            .map(|(name, state)| (name.clone(), Arc::clone(state)))
+    }
+
+    /// Catch every filter up on rows added since its watermark, without a full rebuild.
+    /// Lists created meanwhile are built from scratch; deletions are left to `resync`.
+    #[instrument(skip_all)]
+    async fn catch_up(self: &Arc<Self>) -> Result<()> {
+        let mut caught_up = 0;
+        for (name, state, is_new) in self.reconcile_lists().await? {
+            self.reload_schedule(&state).await?;
+            if is_new {
+                self.rebuild_filter(&name, &state, self.sizing.expected_items).await?;
+                continue;
+            }
+
+            // Rows above the watermark, plus an overlap for transactions that were still open
+            let from = AtomicI32::load(&state.watermark, Ordering::Relaxed).saturating_sub(WATERMARK_OVERLAP).max(0);
+            let mut rows = Vec::new();
+            self.stream_entries(&name, state.id, from, 0, |entry| {
+                rows.push((address::filter_key(&entry.chain, &entry.wallet_address), entry.id));
+            })
+            .await?;
+
+            for (key, row_id) in rows {
+                if state.reapply_insert(&key, row_id).await {
+                    caught_up += 1;
+                }
+            }
+            // Deletes notified before this may be of rows the filter never had
+            let generation = state.generation.fetch_add(1, Ordering::Relaxed) + 1;
+            self.announce_generation(&state, generation).await;
+            self.maybe_resize(&name, &state);
+        }
+
+        info!(caught_up, "🔄 [Sync] Caught up on changes made while not listening.");
+        Ok(())
+    }
+
+    /// Safety net for missed notifications: reconcile the set of lists with the DB
+    /// and rebuild every filter from scratch
+    #[instrument(skip_all)]
+    pub async fn resync(self: &Arc<Self>) -> Result<()> {
+        for (name, state, _) in self.reconcile_lists().await? {
+            self.reload_schedule(&state).await?;
+            let capacity = AtomicUsize::load(&state.capacity, Ordering::Relaxed);
+            self.rebuild_filter(&name, &state, capacity).await?;
+        }
+
+        info!("🔄 [Sync] Full resync complete.");
+        Ok(())
+    }
+
+    /// Tell our own listener that a list's filter reached `generation`. Notifications arrive
+    /// in commit order, so deletes notified after this one happened after the swap.
+    /// Best effort: without it, deletes just leave stale positives until the next one.
+    pub(super) async fn announce_generation(&self, state: &ListState, generation: u64) {
+        let payload = serde_json::json!({
+            "op": "rebuilt",
+            "origin": self.instance_id,
+            "list_id": state.id,
+            "generation": generation,
+        });
+        let sent = match self.pool.get().await {
+            Ok(mut conn) => diesel::sql_query("SELECT pg_notify($1, $2)")
+                .bind::<Text, _>(CHANGES_CHANNEL)
+                .bind::<Text, _>(payload.to_string())
+                .execute(&mut conn)
+                .await
+                .map_err(anyhow::Error::from),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = sent {
+            warn!(list_id = state.id, error = %e, "⚠️ Could not announce filter rebuild.");
+        }
+    }
+
+    /// Make the served lists match the DB: drop lists deleted elsewhere (or deleted and
+    /// re-created under a new id) and add new ones with empty filters. Returns every
+    /// list's name and state, and whether it was just added.
+    async fn reconcile_lists(&self) -> Result<Vec<(String, Arc<ListState>, bool)>> {
+        let mut conn = self.pool.get().await?;
+        let db_lists = lists::table
+            .select(models::List::as_select())
+            .load(&mut conn)
+            .await?;
+        drop(conn);
+
+        let mut all_lists = self.lists.write().await;
+        all_lists.retain(|name, state| db_lists.iter().any(|list| &list.name == name && list.id == state.id));
+
+        Ok(db_lists
+            .into_iter()
+            .map(|list| {
+                let is_new = !all_lists.contains_key(&list.name);
+                let state = all_lists
+                    .entry(list.name.clone())
+                    .or_insert_with(|| Arc::new(ListState::new(list.id, &self.sizing, &self.cache_config)));
+                (list.name, Arc::clone(state), is_new)
+            })
+            .collect())
+    }
+}
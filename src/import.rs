@@ -0,0 +1,365 @@
+//! Bulk loading of wallets from CSV or JSON lines.
+//!
+//! Input is consumed as a byte stream and split into lines as it arrives, so a
+//! file of any size is imported with a bounded amount of memory; lines longer
+//! than `MAX_LINE_LEN` are reported as invalid and skipped. Valid rows are
+//! inserted in batches with `ON CONFLICT DO NOTHING`; rows that were already on
+//! the list (or repeated in the file) are counted as duplicates.
+
+use anyhow::Result;
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::address::{Chain, WalletAddress};
+use crate::guard::AllowlistGuard;
+
+// Rows per INSERT
+const IMPORT_BATCH_SIZE: usize = 1_000;
+// Only the first invalid rows are reported in detail, the rest are just counted
+const MAX_REPORTED_ERRORS: usize = 100;
+// Longest line buffered, in bytes; anything longer is rejected up to its newline
+const MAX_LINE_LEN: usize = 16 * 1024;
+
+/// Layout of an import file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    /// `wallet[,chain]` per line, with an optional `wallet,chain` header.
+    /// Extra columns are ignored.
+    #[default]
+    Csv,
+    /// One `{"wallet": "...", "chain": "..."}` object per line, `chain` optional
+    Jsonl,
+}
+
+#[derive(Deserialize)]
+struct JsonRow {
+    wallet: String,
+    #[serde(default)]
+    chain: Option<String>,
+}
+
+/// A row that could not be imported
+#[derive(Debug, Serialize)]
+pub struct RowError {
+    /// 1-based line number in the input
+    pub line: usize,
+    pub error: String,
+}
+
+/// Outcome of an import
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    /// Non-blank, non-header lines read
+    pub rows: usize,
+    /// Wallets newly added to the list
+    pub inserted: usize,
+    /// Valid wallets that were already on the list or repeated in the input
+    pub duplicates: usize,
+    /// Rows that failed to parse or validate
+    pub invalid: usize,
+    /// Details of the first `MAX_REPORTED_ERRORS` invalid rows
+    pub errors: Vec<RowError>,
+}
+
+/// Accumulates parsed rows and flushes them to the guard in batches
+struct Importer<'a> {
+    guard: &'a Arc<AllowlistGuard>,
+    list: &'a str,
+    format: ImportFormat,
+    default_chain: Chain,
+    tier: Option<&'a str>,
+    batch: Vec<WalletAddress>,
+    report: ImportReport,
+}
+
+impl Importer<'_> {
+    async fn push(&mut self, line: Line) -> Result<()> {
+        let (line_no, raw) = match line {
+            Line::Complete(line_no, raw) => (line_no, raw),
+            Line::TooLong(line_no) => {
+                self.reject(line_no, too_long());
+                return Ok(());
+            }
+        };
+
+        match parse_line(self.format, self.default_chain, line_no, &raw) {
+            Ok(None) => {}
+            Ok(Some(wallet)) => {
+                self.report.rows += 1;
+                self.batch.push(wallet);
+                if self.batch.len() >= IMPORT_BATCH_SIZE {
+                    self.flush().await?;
+                }
+            }
+            Err(error) => self.reject(line_no, error),
+        }
+
+        Ok(())
+    }
+
+    fn reject(&mut self, line_no: usize, error: String) {
+        self.report.rows += 1;
+        self.report.invalid += 1;
+        if self.report.errors.len() < MAX_REPORTED_ERRORS {
+            self.report.errors.push(RowError { line: line_no, error });
+        }
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        let inserted = self.guard.insert_batch(self.list, &self.batch, self.tier).await?;
+        self.report.inserted += inserted;
+        self.report.duplicates += self.batch.len() - inserted;
+        self.batch.clear();
+        Ok(())
+    }
+}
+
+/// Import every wallet in `input` into `list`.
+/// Rows without a chain use `default_chain`; new wallets go into `tier` (the default tier if `None`).
+pub async fn import<S, B, E>(
+    guard: &Arc<AllowlistGuard>,
+    list: &str,
+    format: ImportFormat,
+    default_chain: Chain,
+    tier: Option<&str>,
+    mut input: S,
+) -> Result<ImportReport>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<anyhow::Error>,
+{
+    let mut importer = Importer {
+        guard,
+        list,
+        format,
+        default_chain,
+        tier,
+        batch: Vec::with_capacity(IMPORT_BATCH_SIZE),
+        report: ImportReport::default(),
+    };
+
+    let mut lines = LineSplitter::default();
+    while let Some(chunk) = input.next().await {
+        for line in lines.feed(chunk.map_err(Into::into)?.as_ref()) {
+            importer.push(line).await?;
+        }
+    }
+    if let Some(line) = lines.finish() {
+        importer.push(line).await?;
+    }
+    // Always runs, so an unknown list is reported even for an empty file
+    importer.flush().await?;
+
+    let report = importer.report;
+    info!(
+        list = %list,
+        inserted = report.inserted,
+        duplicates = report.duplicates,
+        invalid = report.invalid,
+        "📥 Imported."
+    );
+    Ok(report)
+}
+
+/// A line of input and its 1-based number
+#[derive(Debug, PartialEq, Eq)]
+enum Line {
+    Complete(usize, Vec<u8>),
+    /// Longer than `MAX_LINE_LEN`; its bytes were dropped
+    TooLong(usize),
+}
+
+/// Splits a byte stream into lines as chunks arrive. Only the bytes a chunk appended
+/// are searched for newlines, and a line that outgrows `MAX_LINE_LEN` is reported
+/// right away and its remaining bytes dropped until the next newline.
+#[derive(Default)]
+struct LineSplitter {
+    pending: Vec<u8>,
+    line_no: usize,
+    // The line being read was already reported as too long
+    oversized: bool,
+}
+
+impl LineSplitter {
+    /// The lines `chunk` completes
+    fn feed(&mut self, chunk: &[u8]) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut scanned = self.pending.len();
+        self.pending.extend_from_slice(chunk);
+
+        let mut start = 0;
+        while let Some(offset) = self.pending[scanned..].iter().position(|&b| b == b'\n') {
+            let end = scanned + offset;
+            if !self.oversized {
+                lines.push(self.line(start, end));
+            }
+            self.oversized = false;
+            start = end + 1;
+            scanned = start;
+        }
+        self.pending.drain(..start);
+
+        if self.pending.len() > MAX_LINE_LEN {
+            if !self.oversized {
+                self.line_no += 1;
+                lines.push(Line::TooLong(self.line_no));
+                self.oversized = true;
+            }
+            self.pending.clear();
+        }
+        lines
+    }
+
+    /// The last line, if the input did not end with a newline
+    fn finish(mut self) -> Option<Line> {
+        if self.pending.is_empty() || self.oversized {
+            return None;
+        }
+        Some(self.line(0, self.pending.len()))
+    }
+
+    fn line(&mut self, start: usize, end: usize) -> Line {
+        self.line_no += 1;
+        let raw = &self.pending[start..end];
+        if raw.len() > MAX_LINE_LEN {
+            return Line::TooLong(self.line_no);
+        }
+        Line::Complete(self.line_no, raw.to_vec())
+    }
+}
+
+/// The wallet on a line, `Ok(None)` for lines that carry no row: blanks and the CSV header
+fn parse_line(format: ImportFormat, default_chain: Chain, line_no: usize, raw: &[u8]) -> Result<Option<WalletAddress>, String> {
+    let line = std::str::from_utf8(raw).map_err(|_| "line is not valid UTF-8".to_string())?.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    let (raw_wallet, raw_chain) = match format {
+        ImportFormat::Csv => {
+            let mut fields = line.split(',').map(|field| field.trim().trim_matches('"'));
+            let raw_wallet = fields.next().unwrap_or_default();
+            if line_no == 1 && matches!(raw_wallet.to_ascii_lowercase().as_str(), "wallet" | "wallet_address" | "address") {
+                return Ok(None);
+            }
+            let raw_chain = fields.next().filter(|field| !field.is_empty());
+            (raw_wallet.to_string(), raw_chain.map(str::to_string))
+        }
+        ImportFormat::Jsonl => {
+            let row: JsonRow = serde_json::from_str(line).map_err(|e| e.to_string())?;
+            (row.wallet, row.chain)
+        }
+    };
+
+    let row_chain = match raw_chain {
+        Some(raw_chain) => raw_chain.parse::<Chain>().map_err(|e| e.to_string())?,
+        None => default_chain,
+    };
+    WalletAddress::parse(row_chain, &raw_wallet)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+fn too_long() -> String {
+    format!("line is longer than {} bytes", MAX_LINE_LEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLET: &str = "0x00000000000000000000000000000000000000aa";
+
+    /// Every line of `chunks`, fed one after the other
+    fn split(chunks: &[&[u8]]) -> Vec<Line> {
+        let mut splitter = LineSplitter::default();
+        let mut lines: Vec<Line> = chunks.iter().flat_map(|chunk| splitter.feed(chunk)).collect();
+        lines.extend(splitter.finish());
+        lines
+    }
+
+    fn complete(line_no: usize, raw: &str) -> Line {
+        Line::Complete(line_no, raw.as_bytes().to_vec())
+    }
+
+    fn parse_csv(line_no: usize, raw: &str) -> Result<Option<WalletAddress>, String> {
+        parse_line(ImportFormat::Csv, Chain::Evm, line_no, raw.as_bytes())
+    }
+
+    fn evm(raw: &str) -> Option<WalletAddress> {
+        Some(WalletAddress::parse(Chain::Evm, raw).unwrap())
+    }
+
+    #[test]
+    fn lines_split_across_chunks_are_joined() {
+        assert_eq!(split(&[b"ab", b"c\nd", b"e\n", b"\nf\n"]), [complete(1, "abc"), complete(2, "de"), complete(3, ""), complete(4, "f")]);
+        assert_eq!(split(&[b"a\nb\nc\n"]), [complete(1, "a"), complete(2, "b"), complete(3, "c")]);
+    }
+
+    #[test]
+    fn a_missing_trailing_newline_still_ends_the_line() {
+        assert_eq!(split(&[b"a\n", b"b"]), [complete(1, "a"), complete(2, "b")]);
+        assert_eq!(split(&[b"a\n"]), [complete(1, "a")]);
+        assert_eq!(split(&[b"", b""]), []);
+    }
+
+    #[test]
+    fn oversized_lines_are_reported_once_and_skipped() {
+        // Within one chunk
+        let mut chunk = vec![b'a'; MAX_LINE_LEN + 1];
+        chunk.extend_from_slice(b"\nok\n");
+        assert_eq!(split(&[&chunk]), [Line::TooLong(1), complete(2, "ok")]);
+
+        // Spanning several chunks, without and with a newline at the end
+        let part = vec![b'a'; MAX_LINE_LEN / 2 + 1];
+        assert_eq!(split(&[b"ok\n", &part, &part, &part]), [complete(1, "ok"), Line::TooLong(2)]);
+        assert_eq!(split(&[&part, &part, &part, b"a\nok"]), [Line::TooLong(1), complete(2, "ok")]);
+
+        // Exactly at the limit is fine
+        let longest = "a".repeat(MAX_LINE_LEN);
+        assert_eq!(split(&[longest.as_bytes(), b"\n"]), [complete(1, &longest)]);
+    }
+
+    #[test]
+    fn crlf_and_padding_are_trimmed() {
+        assert_eq!(parse_csv(1, &format!("{}\r", WALLET)), Ok(evm(WALLET)));
+        assert_eq!(parse_csv(1, &format!("  \"{}\" , evm \r", WALLET)), Ok(evm(WALLET)));
+        assert_eq!(parse_csv(1, "\r"), Ok(None));
+    }
+
+    #[test]
+    fn only_a_first_line_header_is_skipped() {
+        assert_eq!(parse_csv(1, "wallet,chain"), Ok(None));
+        assert_eq!(parse_csv(1, "Address"), Ok(None));
+        assert_eq!(parse_csv(1, "\"wallet_address\",\"chain\""), Ok(None));
+        assert!(parse_csv(2, "wallet,chain").is_err());
+    }
+
+    #[test]
+    fn rows_use_their_chain_or_the_default() {
+        let solana = "11111111111111111111111111111111";
+        assert_eq!(parse_csv(1, &format!("{},solana,extra", solana)), Ok(Some(WalletAddress::parse(Chain::Solana, solana).unwrap())));
+        assert_eq!(parse_line(ImportFormat::Csv, Chain::Solana, 1, solana.as_bytes()), Ok(Some(WalletAddress::parse(Chain::Solana, solana).unwrap())));
+        assert!(parse_csv(1, &format!("{},dogecoin", WALLET)).is_err());
+        assert!(parse_csv(1, solana).is_err());
+    }
+
+    #[test]
+    fn jsonl_rows() {
+        let parse = |raw: &str| parse_line(ImportFormat::Jsonl, Chain::Evm, 1, raw.as_bytes());
+        assert_eq!(parse(&format!("{{\"wallet\": \"{}\"}}\r", WALLET)), Ok(evm(WALLET)));
+        assert_eq!(parse(&format!("{{\"wallet\": \"{}\", \"chain\": \"evm\"}}", WALLET)), Ok(evm(WALLET)));
+        assert_eq!(parse(""), Ok(None));
+        assert!(parse(WALLET).is_err());
+        assert!(parse("{\"chain\": \"evm\"}").is_err());
+    }
+
+    #[test]
+    fn invalid_utf8_is_rejected() {
+        assert_eq!(parse_line(ImportFormat::Csv, Chain::Evm, 1, b"0x\xff"), Err("line is not valid UTF-8".to_string()));
+    }
+}
@@ -0,0 +1,18 @@
+pub mod address;
+pub mod api;
+pub mod cli;
+pub mod config;
+pub mod export;
+pub mod filter;
+pub mod grpc;
+pub mod guard;
+pub mod import;
+pub mod logging;
+pub mod merkle;
+pub mod metrics;
+pub mod migrations;
+pub mod models;
+pub mod schedule;
+pub mod schema;
+pub mod snapshot;
+pub mod voucher;
@@ -0,0 +1,130 @@
+//! Log setup: `tracing` with text or JSON output, levels from `logging.filter`, and
+//! wallet addresses written in full, redacted or hashed depending on `logging.addresses`.
+
+use anyhow::Result;
+use serde::Deserialize;
+use std::fmt;
+use std::sync::OnceLock;
+use tracing_subscriber::EnvFilter;
+
+use crate::address::{self, WalletAddress};
+use crate::config::LoggingConfig;
+use crate::merkle;
+
+/// Shape of log lines
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum LogFormat {
+    /// Human readable
+    #[default]
+    Text,
+    /// One JSON object per line, with span fields
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => anyhow::bail!("'{}' is not a log format, expected text or json", other),
+        }
+    }
+}
+
+impl TryFrom<String> for LogFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: String) -> Result<Self> {
+        raw.parse()
+    }
+}
+
+/// How wallet addresses appear in logs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum AddressLogging {
+    /// The canonical address
+    #[default]
+    Full,
+    /// First 6 and last 4 characters, e.g. `0x5290…9ee7`
+    Redacted,
+    /// A short keccak256 of `chain:address`, stable across restarts so log lines can be correlated
+    Hashed,
+}
+
+impl std::str::FromStr for AddressLogging {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "full" => Ok(AddressLogging::Full),
+            "redacted" => Ok(AddressLogging::Redacted),
+            "hashed" => Ok(AddressLogging::Hashed),
+            other => anyhow::bail!("'{}' is not an address form, expected full, redacted or hashed", other),
+        }
+    }
+}
+
+impl TryFrom<String> for AddressLogging {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: String) -> Result<Self> {
+        raw.parse()
+    }
+}
+
+static ADDRESS_LOGGING: OnceLock<AddressLogging> = OnceLock::new();
+
+/// Install the global subscriber
+pub fn init(config: &LoggingConfig) -> Result<()> {
+    let _ = ADDRESS_LOGGING.set(config.addresses);
+
+    let filter = EnvFilter::try_new(&config.filter)?;
+    // stderr, so admin commands can pipe their output
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr);
+    let result = match config.format {
+        LogFormat::Json => builder.json().flatten_event(true).with_current_span(true).try_init(),
+        LogFormat::Text => builder.try_init(),
+    };
+    result.map_err(|e| anyhow::anyhow!("failed to install the log subscriber: {}", e))
+}
+
+/// A wallet as it should appear in logs
+pub fn wallet(wallet: &WalletAddress) -> LoggedAddress<'_> {
+    LoggedAddress { chain: wallet.chain().as_str(), address: wallet.as_str() }
+}
+
+/// A stored `chain` / `address` pair as it should appear in logs
+pub fn address<'a>(chain: &'a str, address: &'a str) -> LoggedAddress<'a> {
+    LoggedAddress { chain, address }
+}
+
+/// Formats an address according to `logging.addresses`
+pub struct LoggedAddress<'a> {
+    chain: &'a str,
+    address: &'a str,
+}
+
+impl fmt::Display for LoggedAddress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match ADDRESS_LOGGING.get().copied().unwrap_or_default() {
+            AddressLogging::Full => f.write_str(self.address),
+            AddressLogging::Redacted => {
+                let chars: Vec<char> = self.address.chars().collect();
+                if chars.len() <= 10 {
+                    return f.write_str("…");
+                }
+                let head: String = chars[..6].iter().collect();
+                let tail: String = chars[chars.len() - 4..].iter().collect();
+                write!(f, "{}…{}", head, tail)
+            }
+            AddressLogging::Hashed => {
+                let hash = merkle::keccak256(address::filter_key(self.chain, self.address).as_bytes());
+                write!(f, "h:{}", &hex::encode(hash)[..12])
+            }
+        }
+    }
+}
@@ -1,187 +1,104 @@
-pub mod models;
-pub mod schema;
-
-use bloomfilter::Bloom;
-use diesel::prelude::*;
-use diesel_async::{AsyncPgConnection, RunQueryDsl};
-use diesel_async::pooled_connection::deadpool::Pool;
-use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-use std::sync::Arc;
-use tokio::sync::RwLock;
 use dotenv::dotenv;
 use anyhow::Result;
-use rand::Rng;
-
-use schema::bloom_allowlist::dsl::*;
-
-
-
-// --- CONFIGURATION ---
-const EXPECTED_ITEMS: usize = 100_000;
-const FALSE_POSITIVE_RATE: f64 = 0.0001;
-
-/// The "Guard" holds the state
-struct AllowlistGuard {
-    // Diesel Async Pool
-    pool: Pool<AsyncPgConnection>,
-    filter: RwLock<Bloom<String>>,
-}
+use clap::Parser;
+use futures_util::FutureExt;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tracing::info;
+
+use bloom_allowlist_guard::config::AllowlistGuardConfig;
+use bloom_allowlist_guard::guard::AllowlistGuard;
+use bloom_allowlist_guard::cli::{Cli, Command};
+use bloom_allowlist_guard::{api, cli, grpc, logging};
 
-impl AllowlistGuard {
-    /// Initialize: Connect, Migrate Data, and Hydrate Filter
-    async fn new(db_url: &str) -> Result<Arc<Self>> {
-        // 1. Setup Connection Pool
-        let config = AsyncDieselConnectionManager::<AsyncPgConnection>::new(db_url);
-        let pool = Pool::builder(config).build()?;
-
-        println!("Connected to Postgres via Diesel.");
-
-        let guard = Arc::new(Self {
-            pool,
-            filter: RwLock::new(Bloom::new_for_fp_rate(EXPECTED_ITEMS, FALSE_POSITIVE_RATE)),
-        });
-
-        // 2. Run Migration (Add dummy data if needed)
-        guard.migrate_dummy_data(500).await?;
-
-        // 3. Hydrate the Bloom Filter from DB
-        guard.hydrate().await?;
-
-        Ok(guard)
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+    let cli = Cli::parse();
+    // Defaults, then the --config file, then the environment
+    let mut config = AllowlistGuardConfig::builder().file(cli.config.clone()).env().build()?;
+    config.startup.migrate |= cli.migrate;
+    logging::init(&config.logging)?;
+    if let Some(path) = &cli.config {
+        info!(path = %path.display(), "⚙️ Loaded config file.");
     }
 
-    /// Helper to populate the Bloom Filter from the DB
-    async fn hydrate(&self) -> Result<()> {
-        let mut conn = self.pool.get().await?;
-
-        // Diesel Select Query
-        let results = bloom_allowlist
-            .select(models::AllowlistEntry::as_select())
-            .load(&mut conn)
-            .await?;
-
-        // Write to Bloom Filter
-        let mut filter = self.filter.write().await;
-        for entry in &results {
-            filter.set(&entry.wallet_address);
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(&config).await,
+        Command::Migrate => cli::migrate(&config.database.url).await,
+        Command::Hydrate { dry_run } => cli::hydrate(&config, dry_run).await,
+        Command::Admin(command) => {
+            let guard = AllowlistGuard::new(&config).await?;
+            cli::run(&guard, command).await
         }
-
-        println!("🌊 Hydrated Bloom Filter with {} wallets.", results.len());
-        Ok(())
     }
+}
 
-    /// THE REQUESTED FUNCTION: Adds N dummy wallets
-    async fn migrate_dummy_data(&self, count: usize) -> Result<()> {
-        let mut conn = self.pool.get().await?;
-
-        // Check current count to avoid duplicates on restart
-        let current_count: i64 = bloom_allowlist.count().get_result(&mut conn).await?;
-        if current_count >= count as i64 {
-            println!("⏩ Database already has data ({}), skipping migration.", current_count);
-            return Ok(());
-        }
-
-        println!("🏗️ Migrating {} dummy wallets into DB...", count);
-
-        let mut new_entries = Vec::new();
-        for _ in 0..count {
-            // Generate random hex wallet
-            let random_bytes: String = rand::thread_rng()
-                .sample_iter(&rand::distributions::Alphanumeric)
-                .take(40)
-                .map(char::from)
-                .collect();
-
-            new_entries.push(models::NewEntry {
-                wallet_address: format!("0x{}", random_bytes),
-            });
-        }
-
-        // Batch Insert
-        diesel::insert_into(bloom_allowlist)
-            .values(&new_entries)
-            .execute(&mut conn)
-            .await?;
+/// Run the API until Ctrl+C or SIGTERM
+async fn serve(config: &AllowlistGuardConfig) -> Result<()> {
+    let server = &config.server;
 
-        println!("✅ Migration Complete.");
-        Ok(())
+    let guard = AllowlistGuard::new(config).await?;
+    if config.snapshots.dir.is_some() {
+        guard.spawn_snapshot_task(Duration::from_secs(config.snapshots.interval_secs));
     }
-
-    /// The High-Performance Check Logic
-    async fn check_access(&self, wallet_to_check: &str) -> bool {
-        // Step 1: Check Bloom Filter (RAM)
-        let probably_exists = self.filter.read().await.check(&wallet_to_check.to_string());
-
-        if !probably_exists {
-            println!("🛑 [Blocked by Filter] {} is NOT allowlisted.", wallet_to_check);
-            return false;
+    guard.spawn_sync_tasks(Duration::from_secs(config.sync.resync_interval_secs));
+
+    // Both servers drain on the same signal
+    let shutdown = shutdown_signal().boxed().shared();
+
+    let http = async {
+        if server.serve.http() {
+            let listener = TcpListener::bind(&server.listen_addr).await?;
+            info!("🚀 Allowlist guard listening on http://{}", listener.local_addr()?);
+            axum::serve(listener, api::router(guard.clone()))
+                .with_graceful_shutdown(shutdown.clone())
+                .await?;
         }
-
-        // Step 2: Check Postgres (Disk)
-        println!("⚠️ [Filter Passed] Checking DB for {}...", wallet_to_check);
-        let mut conn = self.pool.get().await.expect("Failed to get DB connection");
-
-        // Diesel Query: SELECT count(*) FROM bloom_allowlist WHERE wallet_address = $1
-        let exists: bool = diesel::select(diesel::dsl::exists(
-            bloom_allowlist.filter(wallet_address.eq(wallet_to_check))
-        ))
-            .get_result(&mut conn)
-            .await
-            .unwrap_or(false);
-
-        if exists {
-            println!("✅ [DB Confirmed] Access Granted.");
-        } else {
-            println!("❌ [False Positive] DB rejected the request.");
+        anyhow::Ok(())
+    };
+    let grpc = async {
+        if server.serve.grpc() {
+            let listener = TcpListener::bind(&server.grpc_listen_addr).await?;
+            info!("🚀 Allowlist guard serving gRPC on {}", listener.local_addr()?);
+            tonic::transport::Server::builder()
+                .add_service(grpc::server(guard.clone()))
+                .serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown.clone())
+                .await?;
         }
+        anyhow::Ok(())
+    };
+    tokio::try_join!(http, grpc)?;
 
-        exists
-    }
-
-    /// Add a single user
-    async fn add_user(&self, new_wallet: &str) -> Result<()> {
-        let mut conn = self.pool.get().await?;
-
-        // 1. Insert into DB
-        diesel::insert_into(bloom_allowlist)
-            .values(models::NewEntry { wallet_address: new_wallet.to_string() })
-            .execute(&mut conn)
-            .await?;
+    // Persist the filters so the next boot only has to catch up
+    guard.save_snapshots().await?;
 
-        // 2. Update Filter
-        self.filter.write().await.set(&new_wallet.to_string());
-        println!("➕ Added {} to DB and Bloom Filter.", new_wallet);
-
-        Ok(())
-    }
+    info!("👋 Shutdown complete.");
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    dotenv().ok();
-    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-
-    let guard = AllowlistGuard::new(&db_url).await?;
-
-    println!("\n--- STARTING SIMULATION ---\n");
-
-    // 1. Pick a random wallet from the dummy data to test "Success"
-    let mut conn = guard.pool.get().await?;
-    let random_valid_wallet: String = bloom_allowlist
-        .select(wallet_address)
-        .first(&mut conn)
-        .await?;
-
-    // Test Valid
-    guard.check_access(&random_valid_wallet).await;
-
-    // 2. Test Invalid
-    guard.check_access("0xHackerBot99999").await;
-
-    // 3. Add dynamic user
-    let new_user = "0xVIPUserForAirdrop";
-    guard.add_user(new_user).await?;
-    guard.check_access(new_user).await;
+/// Resolves on Ctrl+C or SIGTERM so in-flight requests can drain
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 
-    Ok(())
-}
\ No newline at end of file
+    info!("🛑 Shutdown signal received, draining connections...");
+}
@@ -0,0 +1,235 @@
+//! Merkle trees over allowlists, for contracts that verify membership on-chain.
+//!
+//! Leaves follow OpenZeppelin's `StandardMerkleTree`: the ABI-encoded value is
+//! hashed twice, `keccak256(bytes.concat(keccak256(abi.encode(value))))`. EVM wallets
+//! are encoded as `address`, every other chain as the canonical address `string`.
+//! Pairs are hashed in sorted order, so proofs verify with `MerkleProof.verify`, and
+//! the tree is laid out like `StandardMerkleTree.of`, so roots match it.
+
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::address::{Chain, WalletAddress};
+
+pub type Hash = [u8; 32];
+
+/// A Merkle tree over a set of leaves, laid out like OpenZeppelin's `makeMerkleTree`:
+/// the root at index 0, the children of node `i` at `2i + 1` and `2i + 2`, and the
+/// sorted, de-duplicated leaves at the end in reverse order.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    nodes: Vec<Hash>,
+}
+
+impl MerkleTree {
+    pub fn new(mut leaves: Vec<Hash>) -> Self {
+        leaves.sort_unstable();
+        leaves.dedup();
+        let Some(last) = (2 * leaves.len()).checked_sub(1) else {
+            return Self { nodes: Vec::new() };
+        };
+
+        let mut nodes = vec![[0u8; 32]; last];
+        for (i, leaf) in leaves.iter().enumerate() {
+            nodes[last - 1 - i] = *leaf;
+        }
+        for i in (0..leaves.len() - 1).rev() {
+            nodes[i] = hash_pair(&nodes[2 * i + 1], &nodes[2 * i + 2]);
+        }
+
+        Self { nodes }
+    }
+
+    /// Root of the tree, all zeros when it is empty
+    pub fn root(&self) -> Hash {
+        self.nodes.first().copied().unwrap_or_default()
+    }
+
+    /// Number of distinct leaves
+    pub fn len(&self) -> usize {
+        self.nodes.len().div_ceil(2)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Sibling hashes from `leaf` up to the root, `None` if the leaf is not in the tree
+    pub fn proof(&self, leaf: &Hash) -> Option<Vec<Hash>> {
+        // The leaves sit at the end in descending order
+        let first_leaf = self.nodes.len() - self.len();
+        let offset = self.nodes[first_leaf..].binary_search_by(|probe| leaf.cmp(probe)).ok()?;
+
+        let mut index = first_leaf + offset;
+        let mut proof = Vec::new();
+        while index > 0 {
+            let sibling = if index % 2 == 1 { index + 1 } else { index - 1 };
+            proof.push(self.nodes[sibling]);
+            index = (index - 1) / 2;
+        }
+        Some(proof)
+    }
+}
+
+/// Everything a contract needs to verify one wallet
+#[derive(Debug, Clone)]
+pub struct MerkleProof {
+    pub root: Hash,
+    pub leaf: Hash,
+    pub proof: Vec<Hash>,
+}
+
+/// Leaf hash of a wallet
+pub fn leaf(wallet: &WalletAddress) -> Hash {
+    let encoded = match wallet.chain() {
+        // abi.encode(address): left-padded to one word
+        Chain::Evm => address_word(wallet).to_vec(),
+        // abi.encode(string): offset, length, then the bytes right-padded to a word boundary
+        _ => {
+            let bytes = wallet.as_str().as_bytes();
+            let padded_len = 64 + bytes.len().div_ceil(32) * 32;
+            let mut encoded = Vec::with_capacity(padded_len);
+            encoded.extend_from_slice(&u256_word(32));
+            encoded.extend_from_slice(&u256_word(bytes.len() as u64));
+            encoded.extend_from_slice(bytes);
+            encoded.resize(padded_len, 0);
+            encoded
+        }
+    };
+
+    keccak256(&keccak256(&encoded))
+}
+
+/// `0x`-prefixed lowercase hex, as Solidity tooling expects
+pub fn to_hex(hash: &Hash) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+pub fn keccak256(data: &[u8]) -> Hash {
+    let mut hash = [0u8; 32];
+    let mut keccak = Keccak::v256();
+    keccak.update(data);
+    keccak.finalize(&mut hash);
+    hash
+}
+
+/// Commutative pair hash used by OpenZeppelin's `MerkleProof`
+fn hash_pair(a: &Hash, b: &Hash) -> Hash {
+    let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+    let mut hash = [0u8; 32];
+    let mut keccak = Keccak::v256();
+    keccak.update(lo);
+    keccak.update(hi);
+    keccak.finalize(&mut hash);
+    hash
+}
+
+/// ABI word of a `uint256` that fits in 64 bits
+pub fn u256_word(value: u64) -> Hash {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// ABI word of an EVM `address`: left-padded to 32 bytes
+pub fn address_word(wallet: &WalletAddress) -> Hash {
+    let mut word = [0u8; 32];
+    hex::decode_to_slice(&wallet.as_str()[2..], &mut word[12..]).expect("canonical EVM addresses are 40 hex digits");
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `MerkleProof.processProof`: fold the proof into the leaf with sorted-pair hashing
+    fn process_proof(leaf: &Hash, proof: &[Hash]) -> Hash {
+        proof.iter().fold(*leaf, |node, sibling| hash_pair(&node, sibling))
+    }
+
+    fn hash_from_hex(hex: &str) -> Hash {
+        let mut hash = [0u8; 32];
+        hex::decode_to_slice(hex.trim_start_matches("0x"), &mut hash).unwrap();
+        hash
+    }
+
+    fn evm(raw: &str) -> WalletAddress {
+        WalletAddress::parse(Chain::Evm, raw).unwrap()
+    }
+
+    #[test]
+    fn matches_the_openzeppelin_readme_tree() {
+        // `StandardMerkleTree.of(values, ["address", "uint256"])` from the @openzeppelin/merkle-tree README
+        let values = [
+            ("0x1111111111111111111111111111111111111111", 5_000_000_000_000_000_000),
+            ("0x2222222222222222222222222222222222222222", 2_500_000_000_000_000_000),
+        ];
+        let leaves: Vec<Hash> = values
+            .iter()
+            .map(|(wallet, amount)| {
+                let encoded = [address_word(&evm(wallet)), u256_word(*amount)].concat();
+                keccak256(&keccak256(&encoded))
+            })
+            .collect();
+
+        let tree = MerkleTree::new(leaves.clone());
+        assert_eq!(
+            to_hex(&tree.root()),
+            "0xd4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77"
+        );
+        for leaf in &leaves {
+            assert_eq!(process_proof(leaf, &tree.proof(leaf).unwrap()), tree.root());
+        }
+    }
+
+    #[test]
+    fn matches_standard_merkle_tree_with_unpaired_nodes() {
+        // `StandardMerkleTree.of([[0x11..11], [0x22..22], ...], ["address"]).root`, computed with
+        // a port of `makeMerkleTree`; trees of 5 and 7 leaves are not perfect binary trees
+        let vectors = [
+            (5, "0xd21ca6424df344c7bdcd3d65c364ffa0cfbe7e04510d81fa57c175716da06376"),
+            (7, "0x4b6ae0080cfe345a991aabf4ecacc546b08ebdc1dc32bbce01e37adf1d82c24f"),
+        ];
+        for (count, root) in vectors {
+            let wallets: Vec<WalletAddress> = (1..=count).map(|n: u8| evm(&format!("0x{}", hex::encode([n * 0x11; 20])))).collect();
+            let tree = MerkleTree::new(wallets.iter().map(leaf).collect());
+            assert_eq!(to_hex(&tree.root()), root, "{} leaves", count);
+            for wallet in &wallets {
+                let leaf = leaf(wallet);
+                assert_eq!(process_proof(&leaf, &tree.proof(&leaf).unwrap()), tree.root());
+            }
+        }
+    }
+
+    #[test]
+    fn evm_leaf_is_the_double_hashed_address_word() {
+        let wallet = evm("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        let word = hash_from_hex("0x0000000000000000000000005aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert_eq!(address_word(&wallet), word);
+        assert_eq!(leaf(&wallet), keccak256(&keccak256(&word)));
+    }
+
+    #[test]
+    fn every_proof_verifies_against_the_root() {
+        // An odd count, so one node moves up without a sibling
+        let wallets: Vec<WalletAddress> = (1..=5u8).map(|n| evm(&format!("0x{}", hex::encode([n; 20])))).collect();
+        let tree = MerkleTree::new(wallets.iter().map(leaf).collect());
+        assert_eq!(tree.len(), 5);
+
+        for wallet in &wallets {
+            let leaf = leaf(wallet);
+            assert_eq!(process_proof(&leaf, &tree.proof(&leaf).unwrap()), tree.root());
+        }
+        assert!(tree.proof(&leaf(&evm("0x6666666666666666666666666666666666666666"))).is_none());
+    }
+
+    #[test]
+    fn empty_and_single_leaf_trees() {
+        assert_eq!(MerkleTree::new(Vec::new()).root(), [0u8; 32]);
+
+        let only = leaf(&evm("0x1111111111111111111111111111111111111111"));
+        let tree = MerkleTree::new(vec![only, only]);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.root(), only);
+        assert_eq!(tree.proof(&only), Some(Vec::new()));
+    }
+}
@@ -0,0 +1,129 @@
+//! Prometheus metrics for guard decisions and filter health.
+//!
+//! Counters and histograms are updated on the hot path; gauges describing the
+//! filters and the pool are refreshed when `/metrics` is scraped.
+
+use anyhow::Result;
+use prometheus::{
+    Gauge, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+    exponential_buckets,
+};
+use std::time::Instant;
+
+use crate::guard::Tier;
+
+/// Every metric the guard exports, registered on its own registry
+pub struct Metrics {
+    registry: Registry,
+    /// Checks turned away because the list was not open
+    pub schedule_rejections: IntCounterVec,
+    /// Checks rejected by the Bloom filter
+    pub filter_rejections: IntCounterVec,
+    /// Filter positives confirmed as members, by Postgres or the cache
+    pub db_confirmations: IntCounterVec,
+    /// Filter positives that turned out not to be on the list
+    pub false_positives: IntCounterVec,
+    /// Failed Postgres lookups
+    pub errors: IntCounterVec,
+    /// `check_access` latency by the tier that decided
+    pub check_duration: HistogramVec,
+    pub filter_items: IntGaugeVec,
+    pub filter_capacity: IntGaugeVec,
+    /// Share of filter counters that are non-zero
+    pub filter_fill_ratio: GaugeVec,
+    /// `fill_ratio ^ k`, the chance that a non-member passes the filter
+    pub filter_estimated_fp_rate: GaugeVec,
+    pub pool_max_size: IntGauge,
+    pub pool_in_use: IntGauge,
+    pub pool_idle: IntGauge,
+    pub pool_waiting: IntGauge,
+    /// `in_use / max_size`
+    pub pool_utilization: Gauge,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+
+        let counter = |name: &str, help: &str, labels: &[&str]| -> Result<IntCounterVec> {
+            let metric = IntCounterVec::new(Opts::new(name, help), labels)?;
+            registry.register(Box::new(metric.clone()))?;
+            Ok(metric)
+        };
+        let int_gauge_vec = |name: &str, help: &str| -> Result<IntGaugeVec> {
+            let metric = IntGaugeVec::new(Opts::new(name, help), &["list"])?;
+            registry.register(Box::new(metric.clone()))?;
+            Ok(metric)
+        };
+        let gauge_vec = |name: &str, help: &str| -> Result<GaugeVec> {
+            let metric = GaugeVec::new(Opts::new(name, help), &["list"])?;
+            registry.register(Box::new(metric.clone()))?;
+            Ok(metric)
+        };
+        let int_gauge = |name: &str, help: &str| -> Result<IntGauge> {
+            let metric = IntGauge::new(name, help)?;
+            registry.register(Box::new(metric.clone()))?;
+            Ok(metric)
+        };
+
+        // From 10µs (a filter rejection) to ~2.6s (a struggling database)
+        let check_duration = HistogramVec::new(
+            HistogramOpts::new("allowlist_check_duration_seconds", "check_access latency by deciding tier")
+                .buckets(exponential_buckets(0.000_01, 4.0, 10)?),
+            &["tier"],
+        )?;
+        registry.register(Box::new(check_duration.clone()))?;
+        let pool_utilization = Gauge::new("allowlist_db_pool_utilization", "Share of the pool's connections in use")?;
+        registry.register(Box::new(pool_utilization.clone()))?;
+
+        Ok(Self {
+            schedule_rejections: counter(
+                "allowlist_schedule_rejections_total",
+                "Checks turned away because the list was not open",
+                &["list"],
+            )?,
+            filter_rejections: counter(
+                "allowlist_filter_rejections_total",
+                "Checks rejected by the Bloom filter",
+                &["list"],
+            )?,
+            db_confirmations: counter(
+                "allowlist_db_confirmations_total",
+                "Filter positives confirmed as members",
+                &["list"],
+            )?,
+            false_positives: counter(
+                "allowlist_false_positives_total",
+                "Filter positives that were not on the list",
+                &["list"],
+            )?,
+            errors: counter("allowlist_errors_total", "Failed Postgres lookups", &["list", "operation"])?,
+            check_duration,
+            filter_items: int_gauge_vec("allowlist_filter_items", "Wallets in the filter")?,
+            filter_capacity: int_gauge_vec("allowlist_filter_capacity", "Wallets the filter is sized for")?,
+            filter_fill_ratio: gauge_vec("allowlist_filter_fill_ratio", "Share of non-zero filter counters")?,
+            filter_estimated_fp_rate: gauge_vec(
+                "allowlist_filter_estimated_fp_rate",
+                "Estimated false positive rate of the filter",
+            )?,
+            pool_max_size: int_gauge("allowlist_db_pool_max_size", "Maximum connections in the pool")?,
+            pool_in_use: int_gauge("allowlist_db_pool_in_use", "Pool connections checked out")?,
+            pool_idle: int_gauge("allowlist_db_pool_idle", "Open pool connections not checked out")?,
+            pool_waiting: int_gauge("allowlist_db_pool_waiting", "Tasks waiting for a pool connection")?,
+            pool_utilization,
+            registry,
+        })
+    }
+
+    /// Record how long a check decided by `tier` took
+    pub fn observe_check(&self, tier: Tier, started: Instant) {
+        self.check_duration
+            .with_label_values(&[tier.as_str()])
+            .observe(started.elapsed().as_secs_f64());
+    }
+
+    /// Everything in the Prometheus text format
+    pub fn encode(&self) -> Result<String> {
+        Ok(TextEncoder::new().encode_to_string(&self.registry.gather())?)
+    }
+}
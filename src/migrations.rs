@@ -0,0 +1,83 @@
+//! Schema migrations, embedded from `migrations/` at build time, so a guard can
+//! migrate its own database and tell whether the schema matches the build.
+
+use anyhow::Result;
+use diesel::connection::SimpleConnection;
+use diesel::migration::MigrationSource;
+use diesel::pg::Pg;
+use diesel::result::Error as DieselError;
+use diesel::sql_types::Text;
+use diesel::{Connection, QueryableByName};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
+
+/// Every migration under `migrations/`
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+// Versions diesel recorded for migrations whose folder was renamed since, and their
+// current version. `create_bloom_allowlist` had no timestamp, so diesel took "create".
+const RENAMED_VERSIONS: &[(&str, &str)] = &[("create", "20261015000000")];
+
+#[derive(QueryableByName)]
+struct AppliedVersion {
+    #[diesel(sql_type = Text)]
+    version: String,
+}
+
+/// Versions of the embedded migrations, oldest first
+pub fn embedded_versions() -> Result<Vec<String>> {
+    let mut versions: Vec<String> = MigrationSource::<Pg>::migrations(&MIGRATIONS)
+        .map_err(|e| anyhow::anyhow!(e))?
+        .iter()
+        .map(|migration| migration.name().version().to_string())
+        .collect();
+    versions.sort();
+    Ok(versions)
+}
+
+/// Versions recorded as applied, renamed ones under their current version.
+/// Empty if no migration was ever run; unlike diesel's harness, this writes nothing.
+pub async fn applied_versions(conn: &mut AsyncPgConnection) -> Result<Vec<String>> {
+    let rows = diesel::sql_query("SELECT version FROM __diesel_schema_migrations ORDER BY version")
+        .load::<AppliedVersion>(conn)
+        .await;
+
+    match rows {
+        Ok(rows) => Ok(rows.into_iter().map(|row| current_version(row.version)).collect()),
+        Err(DieselError::DatabaseError(_, info)) if info.message().contains("does not exist") => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Apply every pending migration, each in its own transaction, and return their versions.
+/// Diesel's harness is synchronous, so this runs on a blocking thread over its own connection.
+pub async fn run_pending(db_url: &str) -> Result<Vec<String>> {
+    let db_url = db_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = AsyncConnectionWrapper::<AsyncPgConnection>::establish(&db_url)?;
+
+        // 1. Creates the version table on a fresh database
+        conn.applied_migrations().map_err(|e| anyhow::anyhow!(e))?;
+
+        // 2. Re-record renamed migrations, or they would run a second time
+        for (old, new) in RENAMED_VERSIONS {
+            conn.batch_execute(&format!(
+                "UPDATE __diesel_schema_migrations SET version = '{}' WHERE version = '{}'",
+                new, old
+            ))?;
+        }
+
+        // 3. Apply the rest in version order
+        let applied = conn.run_pending_migrations(MIGRATIONS).map_err(|e| anyhow::anyhow!(e))?;
+        Ok(applied.iter().map(|version| version.to_string()).collect())
+    })
+    .await?
+}
+
+fn current_version(version: String) -> String {
+    RENAMED_VERSIONS
+        .iter()
+        .find(|(old, _)| *old == version)
+        .map_or(version, |(_, new)| new.to_string())
+}
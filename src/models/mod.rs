@@ -1,15 +1,80 @@
-use crate::schema::bloom_allowlist;
+use crate::schema::{bloom_allowlist, lists, phases};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use diesel::prelude::*;
 
+use crate::address::{AddressError, WalletAddress};
+
     #[derive(Queryable, Selectable)]
     #[diesel(table_name = bloom_allowlist)]
     pub struct AllowlistEntry {
         pub id: i32,
         pub wallet_address: String,
+        pub chain: String,
+    }
+
+    #[derive(Queryable, Selectable)]
+    #[diesel(table_name = bloom_allowlist)]
+    pub struct ExportEntry {
+        pub id: i32,
+        pub chain: String,
+        pub wallet_address: String,
+        pub created_at: Option<NaiveDateTime>,
+    }
+
+    impl ExportEntry {
+        /// Re-validate the stored address; rows written before validation existed may not parse
+        pub fn wallet(&self) -> Result<WalletAddress, AddressError> {
+            WalletAddress::parse(self.chain.parse()?, &self.wallet_address)
+        }
     }
 
     #[derive(Insertable)]
     #[diesel(table_name = bloom_allowlist)]
     pub struct NewEntry {
         pub wallet_address: String,
+        pub list_id: i32,
+        pub chain: String,
+        // `None` leaves the column default (one claim)
+        pub max_claims: Option<i32>,
+        // `None` leaves the column default tier
+        pub tier: Option<String>,
+    }
+
+    #[derive(Queryable, Selectable)]
+    #[diesel(table_name = lists)]
+    pub struct List {
+        pub id: i32,
+        pub name: String,
+    }
+
+    #[derive(Insertable)]
+    #[diesel(table_name = lists)]
+    pub struct NewList {
+        pub name: String,
+    }
+
+    #[derive(Queryable, Selectable)]
+    #[diesel(table_name = lists)]
+    pub struct ListWindow {
+        pub starts_at: Option<DateTime<Utc>>,
+        pub ends_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Queryable, Selectable)]
+    #[diesel(table_name = phases)]
+    pub struct PhaseEntry {
+        pub name: String,
+        pub starts_at: DateTime<Utc>,
+        pub ends_at: Option<DateTime<Utc>>,
+        pub tiers: Vec<String>,
+    }
+
+    #[derive(Insertable)]
+    #[diesel(table_name = phases)]
+    pub struct NewPhase {
+        pub list_id: i32,
+        pub name: String,
+        pub starts_at: DateTime<Utc>,
+        pub ends_at: Option<DateTime<Utc>>,
+        pub tiers: Vec<String>,
     }
@@ -0,0 +1,284 @@
+//! Time-windowed lists: when a list is open, and to which wallet tiers.
+//!
+//! A list may carry its own `starts_at` / `ends_at` window and a set of phases
+//! inside it (OG, whitelist, public, ...). Each phase admits some tiers, or every
+//! tier if it names none. A list without phases is open to everyone for its whole window.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Tier of wallets added without one
+pub const DEFAULT_TIER: &str = "default";
+
+/// One phase of a drop
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Phase {
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    /// `None` keeps the phase open until the list closes
+    #[serde(default)]
+    pub ends_at: Option<DateTime<Utc>>,
+    /// Tiers admitted during the phase; empty admits every tier
+    #[serde(default)]
+    pub tiers: Vec<String>,
+}
+
+impl Phase {
+    /// `None` stands for "any tier"
+    fn admits(&self, tier: Option<&str>) -> bool {
+        match tier {
+            Some(tier) => self.tiers.is_empty() || self.tiers.iter().any(|admitted| admitted == tier),
+            None => true,
+        }
+    }
+
+    fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now && self.ends_at.is_none_or(|end| now < end)
+    }
+}
+
+/// A list's window and phases
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schedule {
+    /// `None` means the list has always been open
+    #[serde(default)]
+    pub starts_at: Option<DateTime<Utc>>,
+    /// `None` means the list never closes
+    #[serde(default)]
+    pub ends_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub phases: Vec<Phase>,
+}
+
+/// Where a list stands at a given moment, for one tier or for any
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Window {
+    /// Open now; `phase` is the running phase, if the list has phases
+    Open { phase: Option<String> },
+    /// Not open yet, opens at `opens_at`
+    NotYetOpen { opens_at: DateTime<Utc> },
+    /// Closed, and no later phase will open it again
+    Closed,
+}
+
+impl Window {
+    pub fn is_open(&self) -> bool {
+        matches!(self, Window::Open { .. })
+    }
+}
+
+/// Errors about a schedule submitted for a list
+#[derive(Debug)]
+pub enum ScheduleError {
+    /// A window that ends before it starts; holds the list or phase name
+    InvalidWindow(String),
+    /// Two phases with the same name
+    DuplicatePhase(String),
+    /// Tiers must be non-empty names
+    InvalidTier(String),
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::InvalidWindow(name) => write!(f, "'{}' must end after it starts", name),
+            ScheduleError::DuplicatePhase(name) => write!(f, "phase '{}' is defined more than once", name),
+            ScheduleError::InvalidTier(tier) => write!(f, "'{}' is not a valid tier", tier),
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+/// Reject tier names that are blank or padded
+pub fn validate_tier(tier: &str) -> Result<(), ScheduleError> {
+    if tier.is_empty() || tier.trim() != tier {
+        return Err(ScheduleError::InvalidTier(tier.to_string()));
+    }
+    Ok(())
+}
+
+impl Schedule {
+    /// Check the windows and names before the schedule is stored
+    pub fn validate(&self, list: &str) -> Result<(), ScheduleError> {
+        if let (Some(start), Some(end)) = (self.starts_at, self.ends_at)
+            && end <= start
+        {
+            return Err(ScheduleError::InvalidWindow(list.to_string()));
+        }
+
+        let mut names = HashSet::new();
+        for phase in &self.phases {
+            if phase.ends_at.is_some_and(|end| end <= phase.starts_at) {
+                return Err(ScheduleError::InvalidWindow(phase.name.clone()));
+            }
+            if !names.insert(phase.name.as_str()) {
+                return Err(ScheduleError::DuplicatePhase(phase.name.clone()));
+            }
+            phase.tiers.iter().try_for_each(|tier| validate_tier(tier))?;
+        }
+
+        Ok(())
+    }
+
+    /// The phase running at `now`, whatever tiers it admits.
+    /// Overlapping phases resolve to the one that started last.
+    pub fn current_phase(&self, now: DateTime<Utc>) -> Option<&Phase> {
+        if !self.in_list_window(now) {
+            return None;
+        }
+        self.phases
+            .iter()
+            .filter(|phase| phase.is_active(now))
+            .max_by_key(|phase| phase.starts_at)
+    }
+
+    /// Where the list stands at `now` for wallets of `tier`, or for any tier if `None`
+    pub fn window(&self, now: DateTime<Utc>, tier: Option<&str>) -> Window {
+        if self.ends_at.is_some_and(|end| now >= end) {
+            return Window::Closed;
+        }
+        if self.phases.is_empty() {
+            return match self.starts_at {
+                Some(start) if now < start => Window::NotYetOpen { opens_at: start },
+                _ => Window::Open { phase: None },
+            };
+        }
+
+        let admitting = || self.phases.iter().filter(|phase| phase.admits(tier));
+        if self.in_list_window(now)
+            && let Some(phase) = admitting().filter(|phase| phase.is_active(now)).max_by_key(|phase| phase.starts_at)
+        {
+            return Window::Open { phase: Some(phase.name.clone()) };
+        }
+
+        // Earliest later moment at which an admitting phase runs inside the list's window
+        let list_start = self.starts_at.unwrap_or(now);
+        let opens_at = admitting()
+            .map(|phase| (phase, phase.starts_at.max(list_start)))
+            .filter(|(phase, opens)| {
+                *opens > now
+                    && phase.ends_at.is_none_or(|end| end > *opens)
+                    && self.ends_at.is_none_or(|end| end > *opens)
+            })
+            .map(|(_, opens)| opens)
+            .min();
+
+        match opens_at {
+            Some(opens_at) => Window::NotYetOpen { opens_at },
+            None => Window::Closed,
+        }
+    }
+
+    fn in_list_window(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at.is_none_or(|start| now >= start) && self.ends_at.is_none_or(|end| now < end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap() + chrono::Duration::hours(hour)
+    }
+
+    fn phase(name: &str, starts: i64, ends: Option<i64>, tiers: &[&str]) -> Phase {
+        Phase {
+            name: name.to_string(),
+            starts_at: at(starts),
+            ends_at: ends.map(at),
+            tiers: tiers.iter().map(|tier| tier.to_string()).collect(),
+        }
+    }
+
+    fn open(phase: &str) -> Window {
+        Window::Open { phase: Some(phase.to_string()) }
+    }
+
+    fn opens(hour: i64) -> Window {
+        Window::NotYetOpen { opens_at: at(hour) }
+    }
+
+    /// List open from hour 10 to 100. `og` starts before the list does, `wl` overlaps
+    /// it, and `public` runs on past the list's end.
+    fn drop_schedule() -> Schedule {
+        Schedule {
+            starts_at: Some(at(10)),
+            ends_at: Some(at(100)),
+            phases: vec![
+                phase("og", 0, Some(20), &["og"]),
+                phase("wl", 15, Some(40), &["og", "wl"]),
+                phase("public", 30, None, &[]),
+            ],
+        }
+    }
+
+    #[test]
+    fn window_by_time_and_tier() {
+        let schedule = drop_schedule();
+        let cases = [
+            // Before the list opens, phases already running wait for the list start
+            (5, None, opens(10)),
+            (5, Some("og"), opens(10)),
+            (5, Some("wl"), opens(15)),
+            (5, Some(DEFAULT_TIER), opens(30)),
+            // Only `og` is running
+            (12, None, open("og")),
+            (12, Some("og"), open("og")),
+            (12, Some("wl"), opens(15)),
+            // `og` and `wl` overlap; the one that started last wins
+            (17, None, open("wl")),
+            (17, Some("og"), open("wl")),
+            (17, Some(DEFAULT_TIER), opens(30)),
+            (35, Some("og"), open("public")),
+            (35, Some(DEFAULT_TIER), open("public")),
+            // `public` has no end of its own, but the list's end closes it
+            (99, Some("wl"), open("public")),
+            (100, None, Window::Closed),
+            (150, Some("og"), Window::Closed),
+        ];
+        for (hour, tier, expected) in cases {
+            assert_eq!(schedule.window(at(hour), tier), expected, "hour {} tier {:?}", hour, tier);
+        }
+    }
+
+    #[test]
+    fn current_phase_by_time() {
+        let schedule = drop_schedule();
+        let cases = [(5, None), (12, Some("og")), (17, Some("wl")), (35, Some("public")), (99, Some("public")), (100, None)];
+        for (hour, expected) in cases {
+            let current = schedule.current_phase(at(hour)).map(|phase| phase.name.as_str());
+            assert_eq!(current, expected, "hour {}", hour);
+        }
+    }
+
+    #[test]
+    fn phases_outside_the_list_window_never_open() {
+        let schedule = Schedule {
+            starts_at: Some(at(10)),
+            ends_at: Some(at(100)),
+            phases: vec![phase("early", 0, Some(5), &["early"]), phase("late", 120, None, &["late"])],
+        };
+        let cases = [(1, Some("early")), (50, Some("late")), (50, None), (130, Some("late"))];
+        for (hour, tier) in cases {
+            assert_eq!(schedule.window(at(hour), tier), Window::Closed, "hour {} tier {:?}", hour, tier);
+        }
+        assert!(schedule.current_phase(at(2)).is_none());
+        assert!(schedule.current_phase(at(130)).is_none());
+    }
+
+    #[test]
+    fn lists_without_phases_follow_their_window() {
+        let schedule = Schedule { starts_at: Some(at(10)), ends_at: Some(at(100)), phases: Vec::new() };
+        let cases = [(5, opens(10)), (10, Window::Open { phase: None }), (100, Window::Closed)];
+        for (hour, expected) in cases {
+            assert_eq!(schedule.window(at(hour), Some("og")), expected, "hour {}", hour);
+        }
+        assert!(Schedule::default().window(at(0), None).is_open());
+    }
+}
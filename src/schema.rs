@@ -5,5 +5,40 @@ diesel::table! {
         id -> Int4,
         wallet_address -> Text,
         created_at -> Nullable<Timestamp>,
+        list_id -> Int4,
+        chain -> Text,
+        max_claims -> Int4,
+        claimed -> Int4,
+        tier -> Text,
     }
 }
+
+diesel::table! {
+    lists (id) {
+        id -> Int4,
+        name -> Text,
+        created_at -> Nullable<Timestamp>,
+        starts_at -> Nullable<Timestamptz>,
+        ends_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    phases (id) {
+        id -> Int4,
+        list_id -> Int4,
+        name -> Text,
+        starts_at -> Timestamptz,
+        ends_at -> Nullable<Timestamptz>,
+        tiers -> Array<Text>,
+    }
+}
+
+diesel::joinable!(bloom_allowlist -> lists (list_id));
+diesel::joinable!(phases -> lists (list_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    bloom_allowlist,
+    lists,
+    phases,
+);
@@ -0,0 +1,176 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::filter::CountingBloom;
+
+// Bump the trailing digit whenever the layout below or the filter key format changes
+const MAGIC: &[u8; 8] = b"BLMSNAP2";
+// Bytes before the filter: magic, list id, watermark, capacity, items
+const HEADER_LEN: u64 = 8 + 4 + 4 + 8 + 8;
+// Bytes of the filter before its counters: k, seed, counter count
+const FILTER_HEADER_LEN: u64 = 4 + 32 + 8;
+// Counters per item a filter may be sized with; 64 allows rates far below 1e-12
+const MAX_COUNTERS_PER_ITEM: usize = 64;
+
+/// A list's filter as persisted to disk, plus the highest `bloom_allowlist.id`
+/// it already covers. On boot only rows above the watermark are loaded.
+///
+/// Layout (little endian): magic, list id (i32), watermark (i32),
+/// capacity (u64), items (u64), then the filter itself.
+pub struct FilterSnapshot {
+    pub list_id: i32,
+    pub watermark: i32,
+    pub capacity: usize,
+    pub items: usize,
+    pub filter: CountingBloom,
+}
+
+impl FilterSnapshot {
+    /// Where the snapshot of a list lives inside `dir`
+    pub fn path_for(dir: &Path, list_id: i32) -> PathBuf {
+        dir.join(format!("list-{}.bloom", list_id))
+    }
+
+    /// Write to a temp file next to `path`, then rename over it,
+    /// so a crash mid-write never leaves a truncated snapshot behind
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let tmp = path.with_extension("bloom.tmp");
+        let mut w = BufWriter::new(File::create(&tmp)?);
+        w.write_all(MAGIC)?;
+        w.write_all(&self.list_id.to_le_bytes())?;
+        w.write_all(&self.watermark.to_le_bytes())?;
+        w.write_all(&(self.capacity as u64).to_le_bytes())?;
+        w.write_all(&(self.items as u64).to_le_bytes())?;
+        self.filter.write_to(&mut w)?;
+        w.into_inner()?.sync_all()?;
+
+        fs::rename(&tmp, path)
+    }
+
+    /// Load a snapshot, `Ok(None)` if there is none yet.
+    /// A corrupt or truncated file is an error, never a huge allocation.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let file_len = file.metadata()?.len();
+        let mut r = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a filter snapshot"));
+        }
+
+        let mut i32_buf = [0u8; 4];
+        let mut u64_buf = [0u8; 8];
+        r.read_exact(&mut i32_buf)?;
+        let list_id = i32::from_le_bytes(i32_buf);
+        r.read_exact(&mut i32_buf)?;
+        let watermark = i32::from_le_bytes(i32_buf);
+        r.read_exact(&mut u64_buf)?;
+        let capacity = u64::from_le_bytes(u64_buf) as usize;
+        r.read_exact(&mut u64_buf)?;
+        let items = u64::from_le_bytes(u64_buf) as usize;
+
+        // The counters must fit in what is left of the file, and suit the capacity
+        let remaining = file_len.saturating_sub(HEADER_LEN + FILTER_HEADER_LEN);
+        let max_counters = usize::try_from(remaining)
+            .unwrap_or(usize::MAX)
+            .min(capacity.saturating_mul(MAX_COUNTERS_PER_ITEM));
+        let filter = CountingBloom::read_from(&mut r, max_counters)?;
+
+        Ok(Some(Self { list_id, watermark, capacity, items, filter }))
+    }
+
+    /// Drop the snapshot of a deleted list
+    pub fn remove(path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    /// A fresh directory under the system temp dir, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("bloom-snapshot-test-{:016x}", rand::thread_rng().next_u64()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn snapshot() -> FilterSnapshot {
+        let mut filter = CountingBloom::new_for_fp_rate(1_000, 0.001);
+        for i in 0..100 {
+            filter.set(format!("evm:0x{:040x}", i).as_str());
+        }
+        FilterSnapshot { list_id: 3, watermark: 42, capacity: 1_000, items: 100, filter }
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = TempDir::new();
+        let path = FilterSnapshot::path_for(&dir.0, 3);
+        let saved = snapshot();
+        saved.save(&path).unwrap();
+
+        let loaded = FilterSnapshot::load(&path).unwrap().unwrap();
+        assert_eq!((loaded.list_id, loaded.watermark, loaded.capacity, loaded.items), (3, 42, 1_000, 100));
+        for i in 0..1_000 {
+            let key = format!("evm:0x{:040x}", i);
+            assert_eq!(loaded.filter.check(key.as_str()), saved.filter.check(key.as_str()));
+        }
+        assert!(!path.with_extension("bloom.tmp").exists());
+    }
+
+    #[test]
+    fn missing_snapshot_is_none() {
+        let dir = TempDir::new();
+        assert!(FilterSnapshot::load(&FilterSnapshot::path_for(&dir.0, 1)).unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupt_snapshots_are_errors() {
+        let dir = TempDir::new();
+        let path = FilterSnapshot::path_for(&dir.0, 3);
+        snapshot().save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        // A counter count far beyond the file, as a flipped bit could produce
+        let mut huge = bytes.clone();
+        let at = (HEADER_LEN + FILTER_HEADER_LEN) as usize - 8;
+        huge[at..at + 8].copy_from_slice(&(1u64 << 60).to_le_bytes());
+        fs::write(&path, &huge).unwrap();
+        assert_eq!(FilterSnapshot::load(&path).err().unwrap().kind(), io::ErrorKind::InvalidData);
+
+        fs::write(&path, &bytes[..bytes.len() - 10]).unwrap();
+        assert!(FilterSnapshot::load(&path).is_err());
+
+        let mut wrong_magic = bytes;
+        wrong_magic[0] = b'X';
+        fs::write(&path, &wrong_magic).unwrap();
+        assert_eq!(FilterSnapshot::load(&path).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,236 @@
+//! EIP-712 mint vouchers: the guard's "allowed" verdict, signed so a mint
+//! contract can check it with `ecrecover` instead of storing the list on-chain.
+//!
+//! The signed struct is
+//! `MintVoucher(address wallet,uint256 listId,uint256 maxQuantity,uint256 nonce,uint256 expiry)`
+//! under the domain `EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)`.
+
+use k256::ecdsa::{SigningKey, VerifyingKey};
+use rand::RngCore;
+use serde::Serialize;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::address::{Chain, WalletAddress};
+use crate::merkle::{self, Hash, address_word, keccak256, u256_word};
+
+const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+const VOUCHER_TYPE: &str = "MintVoucher(address wallet,uint256 listId,uint256 maxQuantity,uint256 nonce,uint256 expiry)";
+
+/// Why a voucher could not be issued
+#[derive(Debug)]
+pub enum VoucherError {
+    /// No signing key is configured
+    Disabled,
+    /// Vouchers are verified with `ecrecover`, so only EVM wallets can get one
+    UnsupportedChain(Chain),
+    /// The configured key or domain is unusable
+    InvalidConfig(String),
+    /// The quantity asked for is 0 or above `allowed`: the configured cap, or the
+    /// wallet's remaining claims if fewer
+    QuantityNotAllowed { requested: u64, allowed: u64 },
+}
+
+impl fmt::Display for VoucherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoucherError::Disabled => write!(f, "voucher signing is not configured"),
+            VoucherError::UnsupportedChain(chain) => write!(f, "vouchers are only issued for evm wallets, not {}", chain),
+            VoucherError::InvalidConfig(reason) => write!(f, "invalid voucher configuration: {}", reason),
+            VoucherError::QuantityNotAllowed { allowed: 0, .. } => write!(f, "the wallet has no claims left"),
+            VoucherError::QuantityNotAllowed { requested, allowed } => {
+                write!(f, "max_quantity must be between 1 and {}, got {}", allowed, requested)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VoucherError {}
+
+/// A signed voucher, in the form a mint transaction passes to the contract
+#[derive(Debug, Clone, Serialize)]
+pub struct MintVoucher {
+    pub wallet: String,
+    pub list_id: i32,
+    pub max_quantity: u64,
+    /// 32 random bytes, so the contract can mark vouchers as used
+    pub nonce: String,
+    /// Unix seconds after which the contract must reject the voucher
+    pub expiry: u64,
+    /// EIP-712 digest that was signed
+    pub digest: String,
+    /// 65-byte `r || s || v` signature, `v` in {27, 28}
+    pub signature: String,
+}
+
+/// Holds the local secp256k1 key and the EIP-712 domain it signs for
+pub struct VoucherSigner {
+    key: SigningKey,
+    domain_separator: Hash,
+    signer: WalletAddress,
+    ttl: Duration,
+    max_quantity: u64,
+}
+
+impl VoucherSigner {
+    /// `key_hex` is the 32-byte private key as hex, with or without `0x`.
+    /// No voucher is signed for more than `max_quantity`.
+    pub fn new(
+        key_hex: &str,
+        domain_name: &str,
+        domain_version: &str,
+        chain_id: u64,
+        verifying_contract: &WalletAddress,
+        ttl: Duration,
+        max_quantity: u64,
+    ) -> Result<Self, VoucherError> {
+        let bytes = hex::decode(key_hex.trim().trim_start_matches("0x"))
+            .map_err(|e| VoucherError::InvalidConfig(format!("signing key is not hex: {}", e)))?;
+        let key = SigningKey::from_slice(&bytes)
+            .map_err(|_| VoucherError::InvalidConfig("signing key is not a valid secp256k1 key".to_string()))?;
+        if verifying_contract.chain() != Chain::Evm {
+            return Err(VoucherError::InvalidConfig("verifying contract must be an evm address".to_string()));
+        }
+
+        let domain_separator = keccak256(&[
+            keccak256(DOMAIN_TYPE.as_bytes()),
+            keccak256(domain_name.as_bytes()),
+            keccak256(domain_version.as_bytes()),
+            u256_word(chain_id),
+            address_word(verifying_contract),
+        ]
+        .concat());
+
+        let signer = eth_address(key.verifying_key());
+        Ok(Self { key, domain_separator, signer, ttl, max_quantity })
+    }
+
+    /// Address the contract should expect `ecrecover` to return
+    pub fn signer(&self) -> &WalletAddress {
+        &self.signer
+    }
+
+    /// Most a single voucher may allow
+    pub fn max_quantity(&self) -> u64 {
+        self.max_quantity
+    }
+
+    /// Sign a voucher for `wallet` on list `list_id`, valid for the configured TTL
+    pub fn sign(&self, wallet: &WalletAddress, list_id: i32, max_quantity: u64) -> Result<MintVoucher, VoucherError> {
+        if wallet.chain() != Chain::Evm {
+            return Err(VoucherError::UnsupportedChain(wallet.chain()));
+        }
+
+        let mut nonce = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let expiry = (SystemTime::now() + self.ttl)
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock is before 1970")
+            .as_secs();
+
+        let digest = self.digest(wallet, list_id, max_quantity, &nonce, expiry);
+        let (signature, recovery_id) = self
+            .key
+            .sign_prehash_recoverable(&digest)
+            .map_err(|e| VoucherError::InvalidConfig(e.to_string()))?;
+        let mut rsv = signature.to_bytes().to_vec();
+        rsv.push(27 + recovery_id.to_byte());
+
+        Ok(MintVoucher {
+            wallet: wallet.to_string(),
+            list_id,
+            max_quantity,
+            nonce: merkle::to_hex(&nonce),
+            expiry,
+            digest: merkle::to_hex(&digest),
+            signature: format!("0x{}", hex::encode(rsv)),
+        })
+    }
+
+    /// EIP-712 digest of a voucher: `keccak256(0x1901 || domainSeparator || hashStruct(voucher))`
+    fn digest(&self, wallet: &WalletAddress, list_id: i32, max_quantity: u64, nonce: &Hash, expiry: u64) -> Hash {
+        let struct_hash = keccak256(&[
+            keccak256(VOUCHER_TYPE.as_bytes()),
+            address_word(wallet),
+            u256_word(list_id as u64),
+            u256_word(max_quantity),
+            *nonce,
+            u256_word(expiry),
+        ]
+        .concat());
+        keccak256(&[&[0x19, 0x01][..], &self.domain_separator, &struct_hash].concat())
+    }
+}
+
+/// The Ethereum address of a key: last 20 bytes of keccak256(uncompressed pubkey)
+fn eth_address(key: &VerifyingKey) -> WalletAddress {
+    let public = key.to_encoded_point(false);
+    let hash = keccak256(&public.as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    WalletAddress::from_bytes(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::{RecoveryId, Signature};
+
+    // Private key 1, whose address is well known, and the domain from the EIP-712 example
+    const KEY: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+    const KEY_ADDRESS: &str = "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf";
+    const VERIFYING_CONTRACT: &str = "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC";
+
+    fn evm(raw: &str) -> WalletAddress {
+        WalletAddress::parse(Chain::Evm, raw).unwrap()
+    }
+
+    fn signer() -> VoucherSigner {
+        VoucherSigner::new(KEY, "Ether Mail", "1", 1, &evm(VERIFYING_CONTRACT), Duration::from_secs(300), 5).unwrap()
+    }
+
+    #[test]
+    fn signer_is_the_address_of_the_key() {
+        assert_eq!(signer().signer().as_str(), KEY_ADDRESS);
+    }
+
+    #[test]
+    fn domain_separator_matches_the_eip712_example() {
+        assert_eq!(
+            merkle::to_hex(&signer().domain_separator),
+            "0xf2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+    }
+
+    #[test]
+    fn digest_of_a_fixed_voucher() {
+        let wallet = evm("0x70997970C51812dc3A010C7d01b50e0d17dc79C8");
+        let digest = signer().digest(&wallet, 7, 3, &[0x42; 32], 1_900_000_000);
+        assert_eq!(
+            merkle::to_hex(&digest),
+            "0xda4f198379a4e335894151ff1e76ede29f816da6380a676afabb251102953695"
+        );
+    }
+
+    #[test]
+    fn signature_recovers_to_the_signer() {
+        let signer = signer();
+        let voucher = signer.sign(&evm("0x70997970C51812dc3A010C7d01b50e0d17dc79C8"), 7, 3).unwrap();
+
+        let mut digest = [0u8; 32];
+        hex::decode_to_slice(&voucher.digest[2..], &mut digest).unwrap();
+        let rsv = hex::decode(&voucher.signature[2..]).unwrap();
+        assert_eq!(rsv.len(), 65);
+
+        let signature = Signature::from_slice(&rsv[..64]).unwrap();
+        let recovery_id = RecoveryId::from_byte(rsv[64] - 27).unwrap();
+        let recovered = VerifyingKey::recover_from_prehash(&digest, &signature, recovery_id).unwrap();
+        assert_eq!(&eth_address(&recovered), signer.signer());
+    }
+
+    #[test]
+    fn only_evm_wallets_get_vouchers() {
+        let wallet = WalletAddress::parse(Chain::Solana, "11111111111111111111111111111111").unwrap();
+        assert!(matches!(signer().sign(&wallet, 7, 1), Err(VoucherError::UnsupportedChain(Chain::Solana))));
+    }
+}
@@ -0,0 +1,173 @@
+//! The gRPC service end to end, through an in-process tonic client, against the
+//! Postgres in DATABASE_URL. Each test works on its own throwaway list.
+//! Ignored by default; run them with `cargo test -- --ignored` once DATABASE_URL
+//! points at a migrated database. They fail, rather than pass, without one.
+
+use std::sync::Arc;
+use rand::RngCore;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::Code;
+use tonic::transport::{Channel, Server};
+
+use bloom_allowlist_guard::address::WalletAddress;
+use bloom_allowlist_guard::grpc::{self, pb};
+use bloom_allowlist_guard::guard::{AllowlistGuard, GuardMode};
+use pb::allowlist_guard_client::AllowlistGuardClient;
+
+/// A guard with a fresh list, served on a random local port, and a client connected to it
+struct Harness {
+    guard: Arc<AllowlistGuard>,
+    client: AllowlistGuardClient<Channel>,
+    list: String,
+}
+
+impl Harness {
+    async fn start() -> Self {
+        dotenv::dotenv().ok();
+        let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must point at a migrated database");
+        let guard = AllowlistGuard::builder(&db_url).mode(GuardMode::Test).build().await.unwrap();
+        let list = format!("grpc-test-{}", rand::thread_rng().next_u64());
+        guard.create_list(&list).await.unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(grpc::server(Arc::clone(&guard)))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let client = AllowlistGuardClient::connect(format!("http://{}", addr)).await.unwrap();
+
+        Self { guard, client, list }
+    }
+
+    async fn stop(self) {
+        self.guard.delete_list(&self.list).await.unwrap();
+    }
+
+    async fn add(&mut self, wallet: &str) -> pb::AddResponse {
+        let request = pb::AddRequest { list: self.list.clone(), wallet: wallet.to_string(), ..Default::default() };
+        self.client.add(request).await.unwrap().into_inner()
+    }
+
+    async fn check(&mut self, wallet: &str) -> pb::CheckResponse {
+        let request = pb::CheckRequest { list: self.list.clone(), wallet: wallet.to_string(), ..Default::default() };
+        self.client.check(request).await.unwrap().into_inner()
+    }
+
+    async fn remove(&mut self, wallet: &str) -> bool {
+        let request = pb::RemoveRequest { list: self.list.clone(), wallet: wallet.to_string(), ..Default::default() };
+        self.client.remove(request).await.unwrap().into_inner().removed
+    }
+}
+
+fn random_wallet() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    WalletAddress::from_bytes(bytes).to_string()
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres in DATABASE_URL"]
+async fn add_check_and_remove() {
+    let mut harness = Harness::start().await;
+    let wallet = random_wallet();
+
+    assert_eq!(harness.add(&wallet).await.wallet, wallet);
+
+    let first = harness.check(&wallet).await;
+    assert!(first.allowed);
+    assert_eq!(first.outcome(), pb::Outcome::Allowed);
+    assert_eq!(first.decided_by(), pb::Tier::Database);
+    assert_eq!(first.window.unwrap().status(), pb::WindowStatus::Open);
+    assert_eq!(harness.check(&wallet).await.decided_by(), pb::Tier::Cache);
+
+    let stats = harness.client.stats(pb::StatsRequest { list: harness.list.clone() }).await.unwrap().into_inner();
+    assert_eq!(stats.items, 1);
+    assert_eq!(stats.cache_hits, 1);
+
+    assert!(harness.remove(&wallet).await);
+    let after = harness.check(&wallet).await;
+    assert!(!after.allowed);
+    assert_eq!(after.outcome(), pb::Outcome::Denied);
+    assert!(!harness.remove(&wallet).await);
+
+    harness.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres in DATABASE_URL"]
+async fn check_batch_keeps_request_order() {
+    let mut harness = Harness::start().await;
+    let member = random_wallet();
+    let stranger = random_wallet();
+    harness.add(&member).await;
+
+    let request = pb::CheckBatchRequest {
+        list: harness.list.clone(),
+        chain: String::new(),
+        wallets: vec![stranger.clone(), "nope".to_string(), member.clone()],
+    };
+    let results = harness.client.check_batch(request).await.unwrap().into_inner().results;
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].wallet, stranger);
+    assert!(!results[0].allowed);
+    assert_eq!(results[1].wallet, "nope");
+    assert!(results[1].error.is_some());
+    assert_eq!(results[2].wallet, member);
+    assert!(results[2].allowed);
+    assert_eq!(results[2].outcome(), pb::Outcome::Allowed);
+
+    harness.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres in DATABASE_URL"]
+async fn errors_map_to_status_codes() {
+    let mut harness = Harness::start().await;
+    let wallet = random_wallet();
+    harness.add(&wallet).await;
+
+    let unknown_list = pb::CheckRequest { list: format!("{}-missing", harness.list), wallet: wallet.clone(), ..Default::default() };
+    assert_eq!(harness.client.check(unknown_list).await.unwrap_err().code(), Code::NotFound);
+
+    let bad_address = pb::CheckRequest { list: harness.list.clone(), wallet: "0x123".to_string(), ..Default::default() };
+    assert_eq!(harness.client.check(bad_address).await.unwrap_err().code(), Code::InvalidArgument);
+
+    let bad_chain = pb::CheckRequest { list: harness.list.clone(), chain: "dogecoin".to_string(), wallet: wallet.clone() };
+    assert_eq!(harness.client.check(bad_chain).await.unwrap_err().code(), Code::InvalidArgument);
+
+    let duplicate = pb::AddRequest { list: harness.list.clone(), wallet, ..Default::default() };
+    assert_eq!(harness.client.add(duplicate).await.unwrap_err().code(), Code::AlreadyExists);
+
+    harness.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres in DATABASE_URL"]
+async fn watch_changes_streams_adds_and_removes() {
+    let mut harness = Harness::start().await;
+    let wallet = random_wallet();
+
+    let request = pb::WatchChangesRequest { list: harness.list.clone() };
+    let mut changes = harness.client.watch_changes(request).await.unwrap().into_inner();
+
+    harness.add(&wallet).await;
+    harness.remove(&wallet).await;
+
+    let added = changes.message().await.unwrap().unwrap();
+    assert_eq!(added.list, harness.list);
+    assert_eq!(added.kind(), pb::ChangeKind::Added);
+    assert_eq!(added.chain, "evm");
+    assert_eq!(added.wallet, wallet);
+    let removed = changes.message().await.unwrap().unwrap();
+    assert_eq!(removed.kind(), pb::ChangeKind::Removed);
+    assert_eq!(removed.wallet, wallet);
+
+    let unknown_list = pb::WatchChangesRequest { list: format!("{}-missing", harness.list) };
+    assert_eq!(harness.client.watch_changes(unknown_list).await.unwrap_err().code(), Code::NotFound);
+
+    harness.stop().await;
+}
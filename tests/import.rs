@@ -0,0 +1,52 @@
+//! Import chunked input into the Postgres in DATABASE_URL, on a throwaway list.
+//! Ignored by default; run them with `cargo test -- --ignored` once DATABASE_URL
+//! points at a migrated database.
+
+use futures_util::stream;
+use rand::RngCore;
+use std::sync::Arc;
+
+use bloom_allowlist_guard::address::{Chain, WalletAddress};
+use bloom_allowlist_guard::guard::{AllowlistGuard, GuardMode};
+use bloom_allowlist_guard::import::{self, ImportFormat};
+
+fn random_wallet() -> WalletAddress {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    WalletAddress::from_bytes(bytes)
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres in DATABASE_URL"]
+async fn import_counts_inserts_duplicates_and_invalid_rows() {
+    dotenv::dotenv().ok();
+    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must point at a migrated database");
+    let guard = Arc::new(AllowlistGuard::builder(&db_url).mode(GuardMode::Test).build().await.unwrap());
+    let list = format!("import-test-{}", rand::thread_rng().next_u64());
+    guard.create_list(&list).await.unwrap();
+
+    let (listed, first, second) = (random_wallet(), random_wallet(), random_wallet());
+    guard.add_user(&list, &listed, None, None).await.unwrap();
+
+    // Header, a CRLF row, a row repeated in the file, one already on the list,
+    // a blank and an invalid line, no trailing newline, and lines cut across chunks
+    let input = format!(
+        "wallet,chain\r\n{}\r\n{},evm\n{}\n\n{}\nnot-a-wallet\n{}",
+        first.as_str(),
+        second.as_str(),
+        first.as_str(),
+        listed.as_str(),
+        second.as_str().to_uppercase(),
+    );
+    let chunks: Vec<Result<Vec<u8>, std::io::Error>> = input.as_bytes().chunks(7).map(|chunk| Ok(chunk.to_vec())).collect();
+    let report = import::import(&guard, &list, ImportFormat::Csv, Chain::Evm, None, stream::iter(chunks)).await.unwrap();
+
+    assert_eq!((report.rows, report.inserted, report.duplicates, report.invalid), (6, 2, 3, 1));
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].line, 7);
+    for wallet in [&listed, &first, &second] {
+        assert!(guard.check_access(&list, wallet).await.unwrap().allowed);
+    }
+
+    guard.delete_list(&list).await.unwrap();
+}
@@ -0,0 +1,57 @@
+//! Mint vouchers against the Postgres in DATABASE_URL, on a throwaway list.
+//! Ignored by default; run them with `cargo test -- --ignored` once DATABASE_URL
+//! points at a migrated database.
+
+use rand::RngCore;
+use std::time::Duration;
+
+use bloom_allowlist_guard::address::{Chain, WalletAddress};
+use bloom_allowlist_guard::guard::{AllowlistGuard, GuardMode};
+use bloom_allowlist_guard::voucher::{VoucherError, VoucherSigner};
+
+const KEY: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+const CONTRACT: &str = "0xcccccccccccccccccccccccccccccccccccccccc";
+
+fn random_wallet() -> WalletAddress {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    WalletAddress::from_bytes(bytes)
+}
+
+fn quantity_error(err: anyhow::Error) -> (u64, u64) {
+    match err.downcast_ref::<VoucherError>() {
+        Some(VoucherError::QuantityNotAllowed { requested, allowed }) => (*requested, *allowed),
+        _ => panic!("expected QuantityNotAllowed, got {}", err),
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres in DATABASE_URL"]
+async fn vouchers_use_up_the_wallets_claims() {
+    dotenv::dotenv().ok();
+    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must point at a migrated database");
+    let contract = WalletAddress::parse(Chain::Evm, CONTRACT).unwrap();
+    let signer = VoucherSigner::new(KEY, "Test", "1", 1, &contract, Duration::from_secs(60), 5).unwrap();
+    let guard = AllowlistGuard::builder(&db_url)
+        .mode(GuardMode::Test)
+        .voucher_signer(Some(signer))
+        .build()
+        .await
+        .unwrap();
+    let list = format!("voucher-test-{}", rand::thread_rng().next_u64());
+    guard.create_list(&list).await.unwrap();
+
+    let wallet = random_wallet();
+    guard.add_user(&list, &wallet, Some(3), None).await.unwrap();
+
+    // 3 claims: a voucher for 2, then one for the last, then nothing
+    assert_eq!(guard.issue_voucher(&list, &wallet, Some(2)).await.unwrap().unwrap().max_quantity, 2);
+    assert_eq!(quantity_error(guard.issue_voucher(&list, &wallet, Some(2)).await.unwrap_err()), (2, 1));
+    assert_eq!(guard.issue_voucher(&list, &wallet, None).await.unwrap().unwrap().max_quantity, 1);
+    assert_eq!(quantity_error(guard.issue_voucher(&list, &wallet, None).await.unwrap_err()), (0, 0));
+
+    // Strangers get no voucher at all
+    assert!(guard.issue_voucher(&list, &random_wallet(), None).await.unwrap().is_none());
+
+    guard.delete_list(&list).await.unwrap();
+}